@@ -0,0 +1,78 @@
+use std::collections::BTreeSet;
+use std::iter::once;
+use lr_parsing_tools::grammar::{First, Grammar, Nullable, Symbol, TokenSet};
+
+/// Follow sets as computed before [`TokenSet`], with one `BTreeSet` per
+/// variable, and first sets given as sorted slices of terminals.
+pub fn follow_btree(grammar: &Grammar, nullable: &Nullable, first: &[Vec<usize>]) -> Vec<BTreeSet<Option<usize>>> {
+    let mut follow = vec![BTreeSet::new(); grammar.rules().len()];
+    for follow in &mut follow[grammar.start_var(0)..] {
+        follow.insert(None);
+    }
+
+    let mut done = false;
+    while !done {
+        done = true;
+        for (A, beta) in grammar.productions() {
+            let mut trailer = follow[A].clone();
+            for &symbol in beta.iter().rev() {
+                match symbol {
+                    Symbol::Terminal(b) => {
+                        trailer = once(Some(b)).collect();
+                    }
+                    Symbol::Variable(B) => {
+                        if !trailer.is_subset(&follow[B]) {
+                            follow[B].extend(&trailer);
+                            done = false;
+                        }
+                        if nullable.get(B) {
+                            trailer.extend(first[B].iter().copied().map(Some));
+                        } else {
+                            trailer = first[B].iter().copied().map(Some).collect();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    follow
+}
+
+/// Follow sets as computed before the transitive closure: the same fixpoint
+/// as [`follow_btree`], over [`TokenSet`]s.
+pub fn follow_fixpoint(grammar: &Grammar, nullable: &Nullable, first: &First) -> Vec<TokenSet> {
+    let word_count = grammar.word_count();
+    let mut follow = vec![TokenSet::new(word_count); grammar.rules().len()];
+    for follow in &mut follow[grammar.start_var(0)..] {
+        follow.insert(None);
+    }
+
+    let mut done = false;
+    while !done {
+        done = true;
+        for (A, beta) in grammar.productions() {
+            let mut trailer = follow[A].clone();
+            for &symbol in beta.iter().rev() {
+                match symbol {
+                    Symbol::Terminal(b) => {
+                        trailer = TokenSet::new(word_count);
+                        trailer.insert(Some(b));
+                    }
+                    Symbol::Variable(B) => {
+                        if follow[B].union_with(&trailer) {
+                            done = false;
+                        }
+                        if nullable.get(B) {
+                            trailer.union_with(first.get(B));
+                        } else {
+                            trailer = first.get(B).clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    follow
+}
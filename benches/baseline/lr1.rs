@@ -0,0 +1,123 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::iter::once;
+use std::rc::Rc;
+use lr_parsing_tools::grammar::{First, Grammar, Nullable, Symbol};
+
+/// The canonical LR(1) collection as built before kernels and cached
+/// closures: items carry a single lookahead, every item set is closed by
+/// iterating to a fixpoint, and item sets are compared whole. Returns the
+/// number of states.
+pub fn lr1_state_count(grammar: &Grammar) -> usize {
+    let (first, nullable) = grammar.first_set();
+    Builder { grammar, first, nullable }.build()
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Item {
+    production: usize,
+    pos: usize,
+    lookahead: Option<usize>,
+}
+
+struct Builder<'a> {
+    grammar: &'a Grammar,
+    first: First,
+    nullable: Nullable,
+}
+
+impl Builder<'_> {
+    fn symbol_at(&self, item: &Item, offset: usize) -> Option<Symbol> {
+        self.grammar.productions().get(item.production).1.get(item.pos + offset).copied()
+    }
+
+    fn closure(&self, old_items: &BTreeSet<Item>) -> BTreeSet<Item> {
+        let mut items     = old_items.clone();
+        let mut new_items = old_items.clone();
+
+        let mut done = false;
+        while !done {
+            done = true;
+            for item in &items {
+                if let Some(Symbol::Variable(var)) = self.symbol_at(item, 0) {
+                    let lookaheads: Vec<Option<usize>> = match self.symbol_at(item, 1) {
+                        None => vec![item.lookahead],
+                        Some(Symbol::Terminal(a)) => vec![Some(a)],
+                        Some(Symbol::Variable(A)) => {
+                            let first_A = self.first.get(A).words().map(Some);
+                            if self.nullable.get(A) {
+                                first_A.chain(once(item.lookahead)).collect()
+                            } else {
+                                first_A.collect()
+                            }
+                        },
+                    };
+                    for lookahead in lookaheads {
+                        for production in self.grammar.rules().get(var).production_ids() {
+                            if new_items.insert(Item { production, pos: 0, lookahead }) {
+                                done = false;
+                            }
+                        }
+                    }
+                }
+            }
+            items = new_items.clone();
+        }
+
+        items
+    }
+
+    fn goto<'b, I: Iterator<Item=&'b Item>>(&self, items: I, x: Symbol) -> BTreeSet<Item> {
+        self.closure(&items
+            .filter(|item| self.symbol_at(item, 0) == Some(x))
+            .map(|item| Item { pos: item.pos + 1, ..*item })
+            .collect())
+    }
+
+    fn build(&self) -> usize {
+        let start = Item { production: self.grammar.start_production(0), pos: 0, lookahead: None };
+        let initial_items = Rc::new(self.closure(&once(start).collect()));
+
+        let mut itemsets = vec![initial_items.clone()];
+        let mut gotos: Vec<HashMap<Symbol, usize>> = vec![HashMap::new()];
+        let mut table: HashMap<_, usize> = once((initial_items.clone(), 0)).collect();
+        let mut queue: VecDeque<_> = once(initial_items).collect();
+
+        let mut i = 0_usize;
+        while let Some(item_set) = queue.pop_front() {
+            let mut iter1 = item_set.iter();
+            let mut iter2 = iter1.clone();
+
+            while let Some(item) = iter1.next() {
+                if let Some(x) = self.symbol_at(item, 0) {
+                    if gotos[i].contains_key(&x) {
+                        continue;
+                    }
+
+                    // Items before `iter2` do not contribute to the goto on `x`.
+                    let temp = self.goto(iter2, x);
+                    let j = if let Some(&index) = table.get(&temp) {
+                        index
+                    } else {
+                        let temp = Rc::new(temp);
+                        itemsets.push(temp.clone());
+                        gotos.push(HashMap::new());
+                        table.insert(temp.clone(), itemsets.len() - 1);
+                        queue.push_back(temp);
+                        itemsets.len() - 1
+                    };
+                    gotos[i].insert(x, j);
+
+                    iter2 = iter1.clone();
+                }
+            }
+
+            i += 1;
+        }
+
+        itemsets.len()
+    }
+}
@@ -0,0 +1,8 @@
+//! Implementations the library has replaced, kept so that the benchmarks can
+//! show the gain of their replacements on the same grammars.
+
+mod follow;
+pub use self::follow::{follow_btree, follow_fixpoint};
+
+mod lr1;
+pub use self::lr1::lr1_state_count;
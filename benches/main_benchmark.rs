@@ -1,8 +1,11 @@
-#![allow(non_upper_case_globals)]
+#![allow(non_upper_case_globals, non_snake_case)]
 
 use lr_parsing_tools::grammar::{Symbol, Grammar, GrammarBuilder};
+use lr_parsing_tools::automata::{LALR1A, LR1A, LRAutomaton};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
+mod baseline;
+
 fn rr_expr_grammar() -> Grammar {
     const add: Symbol    = Symbol::Terminal(0);
     const sub: Symbol    = Symbol::Terminal(1);
@@ -44,9 +47,144 @@ fn rr_expr_grammar() -> Grammar {
                          .build().unwrap()
 }
 
+/// A statement language whose expressions have `levels` binary operator
+/// precedence levels, each with its own operator token. Grammar size grows
+/// linearly with `levels`, and automaton size roughly quadratically.
+fn layered_grammar(levels: usize) -> Grammar {
+    const lparen: Symbol = Symbol::Terminal(0);
+    const rparen: Symbol = Symbol::Terminal(1);
+    const lbrace: Symbol = Symbol::Terminal(2);
+    const rbrace: Symbol = Symbol::Terminal(3);
+    const semi: Symbol   = Symbol::Terminal(4);
+    const comma: Symbol  = Symbol::Terminal(5);
+    const assign: Symbol = Symbol::Terminal(6);
+    const if_: Symbol    = Symbol::Terminal(7);
+    const while_: Symbol = Symbol::Terminal(8);
+    const name: Symbol   = Symbol::Terminal(9);
+    const num: Symbol    = Symbol::Terminal(10);
+    let op = |i: usize| Symbol::Terminal(11 + i);
+    // ---
+    const Program: Symbol = Symbol::Variable(0);
+    const Stmt: Symbol    = Symbol::Variable(1);
+    const Args: Symbol    = Symbol::Variable(2);
+    const ArgList: Symbol = Symbol::Variable(3);
+    let level = |i: usize| Symbol::Variable(4 + i);
+    let Expr = level(0);
+
+    // Program : Program Stmt | ,
+    // Stmt : Expr ; | name = Expr ; | { Program } | if ( Expr ) Stmt | while ( Expr ) Stmt,
+    // Args : ArgList | ,
+    // ArgList : ArgList , Expr | Expr,
+    // E_i : E_i op_i E_{i+1} | E_{i+1}, (for i < levels)
+    // E_levels : ( Expr ) | name | num | name ( Args ),
+    let mut builder = GrammarBuilder::new().new_rule().add_production([Program, Stmt])
+                                                      .add_production([])
+                                           .new_rule().add_production([Expr, semi])
+                                                      .add_production([name, assign, Expr, semi])
+                                                      .add_production([lbrace, Program, rbrace])
+                                                      .add_production([if_, lparen, Expr, rparen, Stmt])
+                                                      .add_production([while_, lparen, Expr, rparen, Stmt])
+                                           .new_rule().add_production([ArgList])
+                                                      .add_production([])
+                                           .new_rule().add_production([ArgList, comma, Expr])
+                                                      .add_production([Expr]);
+    for i in 0..levels {
+        builder = builder.new_rule().add_production([level(i), op(i), level(i + 1)])
+                                    .add_production([level(i + 1)]);
+    }
+    builder.new_rule().add_production([lparen, Expr, rparen])
+                      .add_production([name])
+                      .add_production([num])
+                      .add_production([name, lparen, Args, rparen])
+           .build().unwrap()
+}
+
+/// `A_i : t_i A_{i-1} | A_{i-1} t_i`, with `A_0 : t_0` and `A_n` as the entry
+/// point, so follow sets only grow by propagating against the order of the
+/// productions.
+fn chain_grammar(n: usize) -> Grammar {
+    let mut builder = GrammarBuilder::new().new_rule().add_production([Symbol::Terminal(0)]);
+    for i in 1..=n {
+        builder = builder.new_rule().add_production([Symbol::Terminal(i), Symbol::Variable(i - 1)])
+                                    .add_production([Symbol::Variable(i - 1), Symbol::Terminal(i)]);
+    }
+    builder.entry_points([n]).build().unwrap()
+}
+
 pub fn follow_benchmark(c: &mut Criterion) {
     c.bench_function("follow_set", |b| b.iter(|| rr_expr_grammar().follow_set()));
 }
 
-criterion_group!(benches, follow_benchmark);
-criterion_main!(benches);
\ No newline at end of file
+/// Follow sets over `BTreeSet`s, as before [`TokenSet`], against the same
+/// fixpoint over `TokenSet`s.
+///
+/// [`TokenSet`]: lr_parsing_tools::grammar::TokenSet
+pub fn token_set_benchmark(c: &mut Criterion) {
+    for &(label, levels) in &[("large", 40), ("huge", 400)] {
+        let grammar = layered_grammar(black_box(levels));
+        let (first, nullable) = grammar.first_set();
+        let first_words: Vec<Vec<usize>> = (0..grammar.rules().len()).map(|A| first.get(A).words().collect()).collect();
+
+        let btree = baseline::follow_btree(&grammar, &nullable, &first_words);
+        let token_set = baseline::follow_fixpoint(&grammar, &nullable, &first);
+        assert!(btree.iter().zip(&token_set).all(|(old, new)| old.iter().copied().eq(new.iter())));
+
+        let mut group = c.benchmark_group(format!("follow_fixpoint_{}", label));
+        group.sample_size(10);
+        group.bench_function("btree", |b| b.iter(|| baseline::follow_btree(&grammar, &nullable, &first_words)));
+        group.bench_function("token_set", |b| b.iter(|| baseline::follow_fixpoint(&grammar, &nullable, &first)));
+        group.finish();
+    }
+}
+
+pub fn automaton_benchmark(c: &mut Criterion) {
+    let grammar = rr_expr_grammar();
+    c.bench_function("lalr1a", |b| b.iter(|| LALR1A::new(&grammar)));
+    c.bench_function("lr1a", |b| b.iter(|| LR1A::new(&grammar)));
+}
+
+/// The LR(1) builder working from kernels against the closure-based builder
+/// it replaced, on the same grammars.
+pub fn lr1_builder_benchmark(c: &mut Criterion) {
+    let grammars = [("expr", rr_expr_grammar()), ("large", layered_grammar(black_box(40)))];
+    for (label, grammar) in &grammars {
+        assert_eq!(baseline::lr1_state_count(grammar), LR1A::new(grammar).state_count());
+
+        let mut group = c.benchmark_group(format!("lr1a_{}", label));
+        group.sample_size(10);
+        group.bench_function("closure", |b| b.iter(|| baseline::lr1_state_count(grammar)));
+        group.bench_function("kernels", |b| b.iter(|| LR1A::new(grammar)));
+        group.finish();
+    }
+}
+
+/// Follow sets by transitive closure against the fixpoint they replaced,
+/// each including the first sets they depend on.
+pub fn large_grammar_benchmark(c: &mut Criterion) {
+    let grammars = [
+        ("large", layered_grammar(black_box(40))),
+        ("huge", layered_grammar(black_box(400))),
+        ("chain", chain_grammar(black_box(400))),
+    ];
+    for (label, grammar) in &grammars {
+        let (follow, first, nullable) = grammar.follow_set();
+        let fixpoint = baseline::follow_fixpoint(grammar, &nullable, &first);
+        assert!(fixpoint.iter().enumerate().all(|(A, old)| old == follow.get(A)));
+
+        let mut group = c.benchmark_group(format!("follow_set_{}", label));
+        group.sample_size(10);
+        group.bench_function("fixpoint", |b| b.iter(|| {
+            let (first, nullable) = grammar.first_set();
+            baseline::follow_fixpoint(grammar, &nullable, &first)
+        }));
+        group.bench_function("transitive_closure", |b| b.iter(|| grammar.follow_set()));
+        group.finish();
+    }
+
+    let (_, grammar) = &grammars[0];
+    c.bench_function("lalr1a_large", |b| b.iter(|| LALR1A::new(grammar)));
+    c.bench_function("lr1a_large", |b| b.iter(|| LR1A::new(grammar)));
+}
+
+criterion_group!(benches, follow_benchmark, token_set_benchmark, automaton_benchmark, lr1_builder_benchmark, large_grammar_benchmark);
+criterion_main!(benches);
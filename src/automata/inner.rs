@@ -1,11 +1,13 @@
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::iter::once;
-use std::rc::Rc;
+use std::sync::Arc;
 use crate::grammar::Symbol;
+use crate::parallel::{self, MaybeSend, MaybeSync};
+
+pub trait BuildItemSets<T: Ord + std::hash::Hash + std::fmt::Debug + MaybeSend + MaybeSync>: MaybeSync {
+    /// The start item of each entry point, in order.
+    fn start_items(&self) -> Vec<T>;
 
-pub trait BuildItemSets<T: Ord + std::hash::Hash + std::fmt::Debug> {
-    fn start_item(&self) -> T;
-    
     fn advance(&self, item: &T) -> T;
 
     fn symbol_at_dot(&self, item: &T) -> Option<Symbol>;
@@ -26,68 +28,87 @@ pub trait BuildItemSets<T: Ord + std::hash::Hash + std::fmt::Debug> {
         }).collect::<BTreeSet<T>>())
     }
 
-    fn build(&self) -> (Vec<Vec<T>>, Vec<HashMap<Symbol, usize>>) {
-        let initial_items = Rc::new(
-            self.closure(&once(self.start_item()).collect())
-        );
+    /// Computes the item sets reachable from `item_set` by a single
+    /// transition, in symbol order.
+    fn successors(&self, item_set: &BTreeSet<T>) -> BTreeMap<Symbol, BTreeSet<T>> {
+        let mut successors: BTreeMap<Symbol, BTreeSet<T>> = BTreeMap::new();
+
+        let mut iter1 = item_set.iter();
+        let mut iter2 = iter1.clone();
+
+        while let Some(item) = iter1.next() {
+            if let Some(x) = self.symbol_at_dot(item) {
+                // x has already been processed
+                if successors.contains_key(&x) {
+                    continue;
+                }
+
+                // NOTE: Previously processed items in item_set (those before
+                //       iter2) are guaranteed to not contribute to the output
+                //       item set. As such, goto is only required to process
+                //       from iter2 onwards.
+                successors.insert(x, self.goto(iter2, &x));
+
+                iter2 = iter1.clone();
+            }
+        }
+
+        successors
+    }
 
-        let mut itemsets = vec![initial_items.clone()];
-        let mut gotos: Vec<HashMap<Symbol, usize>> = vec![HashMap::new()];
+    fn build(&self) -> (Vec<Vec<T>>, Vec<BTreeMap<Symbol, usize>>) {
+        let mut itemsets: Vec<Arc<BTreeSet<T>>> = Vec::new();
+        let mut gotos: Vec<BTreeMap<Symbol, usize>> = Vec::new();
 
         // Item sets we've seen so far mapped to indices in itemsets vector.
-        let mut table: HashMap<_, usize> = once((initial_items.clone(), 0)).collect();
-
-        // Queue of itemsets to process.
-        // NOTE: A stack could be used here instead; but by using a queue,
-        //       the iteration step of the outer-most loop (i) will correspond
-        //       to the index of the item set in CC we are currently
-        //       transitioning from.
-        let mut queue: VecDeque<_> = once(initial_items).collect();
-
-        let mut i = 0_usize;
-
-        while let Some(item_set) = queue.pop_front() {
-            let mut iter1 = item_set.iter();
-            let mut iter2 = iter1.clone();
-
-            while let Some(item) = iter1.next() {
-                if let Some(x) = self.symbol_at_dot(item) {
-                    // x has already been processed
-                    if gotos[i].contains_key(&x) {
-                        continue;
-                    }
-                    
-                    // NOTE: Previously processed items in item_set (those before
-                    //       iter2) are guaranteed to not contribute to the output
-                    //       item set. As such, goto is only required to process
-                    //       from iter2 onwards.
-                    let temp = self.goto(iter2, &x);
-                    
+        let mut table: HashMap<Arc<BTreeSet<T>>, usize> = HashMap::new();
+
+        // The frontier holds the item sets discovered in the previous round,
+        // in the order they were numbered. Successors of the whole frontier
+        // are computed at once (in parallel, if enabled), and are then
+        // numbered sequentially in frontier order and, for each item set,
+        // in symbol order. This is exactly the order a breadth-first
+        // traversal with a queue would number them in, and it depends only
+        // on the grammar.
+        // The start states of the entry points come first, in order.
+        let mut frontier: Vec<Arc<BTreeSet<T>>> = self.start_items().into_iter()
+            .map(|item| Arc::new(self.closure(&once(item).collect())))
+            .collect();
+        for (i, item_set) in frontier.iter().enumerate() {
+            table.insert(item_set.clone(), i);
+        }
+
+        while !frontier.is_empty() {
+            let all_successors = parallel::map(&frontier, |item_set| self.successors(item_set));
+            let mut new_frontier = Vec::new();
+
+            for (item_set, successors) in frontier.into_iter().zip(all_successors) {
+                let mut next = BTreeMap::new();
+
+                for (x, temp) in successors {
                     // Check if temp is already in itemsets. If not, we
-                    // add to itemsets and push on to process queue.
+                    // add to itemsets and push on to the next frontier.
                     let j = if let Some(&index) = table.get(&temp) {
                         index
                     } else {
-                        let new_index = itemsets.len();
-                        let temp_rc = Rc::new(temp);
-
-                        itemsets.push(temp_rc.clone());
-                        gotos.push(HashMap::new());
+                        let new_index = table.len();
+                        let temp_arc = Arc::new(temp);
 
-                        table.insert(temp_rc.clone(), new_index);
-                        queue.push_back(temp_rc);
+                        table.insert(temp_arc.clone(), new_index);
+                        new_frontier.push(temp_arc);
 
                         new_index
                     };
 
                     // Record transition on x
-                    gotos[i].insert(x, j);
-
-                    iter2 = iter1.clone();
+                    next.insert(x, j);
                 }
+
+                itemsets.push(item_set);
+                gotos.push(next);
             }
 
-            i += 1;
+            frontier = new_frontier;
         }
 
         // forces out-of-scope early so all
@@ -95,11 +116,11 @@ pub trait BuildItemSets<T: Ord + std::hash::Hash + std::fmt::Debug> {
         drop(table);
 
         let itemsets: Vec<Vec<T>> = itemsets.into_iter()
-            .map(Rc::try_unwrap)
+            .map(Arc::try_unwrap)
             .map(Result::unwrap)
             .map(|items| items.into_iter().collect())
             .collect();
 
         (itemsets, gotos)
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,16 @@
 #![allow(non_snake_case)]
 
-use std::collections::{HashSet, HashMap};
-use super::LR0A;
-use super::{LALR1A, StateReductionPair};
-use crate::grammar::{Grammar, Symbol, Nullable};
-use crate::transitive_closure;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use super::{lr0a, LR0A, LRAutomaton};
+use super::StateReductionPair;
+use crate::grammar::{Grammar, Symbol, Nullable, TokenSet};
+use crate::{parallel, transitive_closure};
 
 pub struct LALR1ABuilder<'a> {
     grammar: &'a Grammar,
-    lr0a: LR0A<'a>,
+    lr0a: LR0A<&'a Grammar>,
     nullable: Nullable,
+    word_count: usize,
     nonterminal_transitions: Vec<NonterminalTransition>,
     nonterminal_transition_map: HashMap<NonterminalTransition, usize>,
 }
@@ -42,18 +43,16 @@ impl<'a> LALR1ABuilder<'a> {
             grammar,
             lr0a,
             nullable: grammar.nullability(),
+            word_count: grammar.word_count(),
             nonterminal_transitions,
             nonterminal_transition_map,
         }
     }
 
     #[must_use]
-    pub fn build(self) -> LALR1A<'a> {
+    pub fn build(self) -> (Vec<lr0a::State>, BTreeMap<StateReductionPair, TokenSet>) {
         let lookahead = self.lookahead();
-        LALR1A {
-            lr0a: self.lr0a,
-            lookahead,
-        }
+        (self.lr0a.into_states(), lookahead)
     }
 }
 
@@ -64,31 +63,31 @@ impl LALR1ABuilder<'_> {
     }
 
     #[must_use]
-    pub fn direct_read(&self) -> Vec<HashSet<Option<usize>>> {
+    pub fn direct_read(&self) -> Vec<TokenSet> {
         let states = self.lr0a.states();
-        let mut direct_read: Vec<HashSet<Option<usize>>> = 
-            vec![HashSet::new(); self.nonterminal_transitions.len()];        
-        for (i, &transition) in self.nonterminal_transitions.iter().enumerate() {
+        parallel::map(&self.nonterminal_transitions, |&transition| {
             let NonterminalTransition { state: p, var: A } = transition;
-            // The only "transition" to the "accept state" is from the state
-            // reached by shifting the start variable from the start state.
+            let mut direct_read = TokenSet::new(self.word_count);
+            // The only "transitions" to the "accept state" are from the
+            // states reached by shifting an entry point from its start state.
             // TODO: lift outside the loop
-            if (p, A) == (0, 0) {
-                direct_read[i].insert(None);
+            let entry_points = self.grammar.entry_points().iter().enumerate();
+            if entry_points.filter(|&(_, &var)| var == A).any(|(entry, _)| self.lr0a.start_state(entry) == p) {
+                direct_read.insert(None);
             }
             let q = states[p].next[&Symbol::Variable(A)];
             for &symbol in states[q].next.keys() {
                 if let Symbol::Terminal(t) = symbol {
                     // (p, A) directly-reads t
-                    direct_read[i].insert(Some(t));
+                    direct_read.insert(Some(t));
                 }
             }
-        }
-        direct_read
+            direct_read
+        })
     }
 
     #[must_use]
-    pub fn read(&self) -> Vec<HashSet<Option<usize>>> {
+    pub fn read(&self) -> Vec<TokenSet> {
         let mut read = self.direct_read();
         let reads = self.reads();
         if transitive_closure(&mut read, |i| reads[i].iter().copied(), extend) {
@@ -98,7 +97,7 @@ impl LALR1ABuilder<'_> {
     }
 
     #[must_use]
-    pub fn follow(&self) -> Vec<HashSet<Option<usize>>> {
+    pub fn follow(&self) -> Vec<TokenSet> {
         let mut follow = self.read();
         let includes = self.includes();
         if transitive_closure(&mut follow, |i| includes[i].iter().copied(), extend) {
@@ -108,21 +107,22 @@ impl LALR1ABuilder<'_> {
     }
 
     #[must_use]
-    pub fn lookahead(&self) -> HashMap<StateReductionPair, HashSet<Option<usize>>> {
+    pub fn lookahead(&self) -> BTreeMap<StateReductionPair, TokenSet> {
         let follow = self.follow();
-        self.lookback().into_iter().map(|(key, value)| {
-            (key, value.into_iter().fold(HashSet::new(), |mut acc, x| {
-                acc.extend(&follow[x]);
+        let lookback: Vec<_> = self.lookback().into_iter().collect();
+        parallel::map(&lookback, |(key, value)| {
+            (*key, value.iter().fold(TokenSet::new(self.word_count), |mut acc, &x| {
+                acc.union_with(&follow[x]);
                 acc
             }))
-        }).collect()
+        }).into_iter().collect()
     }
 
     #[must_use]
-    pub fn reads(&self) -> Vec<HashSet<usize>> {
+    pub fn reads(&self) -> Vec<BTreeSet<usize>> {
         // NOTE: this doesn't need to be stored: can be computed on the fly.
         let states = self.lr0a.states();
-        self.nonterminal_transitions.iter().map(|&transition| {
+        parallel::map(&self.nonterminal_transitions, |&transition| {
             let NonterminalTransition { state: p, var: A } = transition;
             let q = states[p].next[&Symbol::Variable(A)];
             states[q].next.keys().filter_map(|&symbol| {
@@ -136,15 +136,16 @@ impl LALR1ABuilder<'_> {
                 }
                 None
             }).collect()
-        }).collect()
+        })
     }
 
     #[must_use]
-    pub fn includes(&self) -> Vec<HashSet<usize>> {
+    pub fn includes(&self) -> Vec<BTreeSet<usize>> {
         let states = self.lr0a.states();
-        let mut successors = vec![HashSet::new(); self.nonterminal_transitions.len()];
-        for transition in self.nonterminal_transitions() {
-            let NonterminalTransition { state: p, var: B } = *transition;
+        // For each transition (p, B), the transitions (q, A) that include it.
+        let included_by = parallel::map(self.nonterminal_transitions(), |&transition| {
+            let NonterminalTransition { state: p, var: B } = transition;
+            let mut included_by = Vec::new();
             for alt in self.grammar.rules().get(B).alts() {
                 let mut q = p;
                 for (i, &symbol) in alt.iter().enumerate() {
@@ -154,20 +155,27 @@ impl LALR1ABuilder<'_> {
                             Symbol::Variable(C) => self.nullable.get(C),
                         });
                         if nullable_gamma {
-                            let i = self.nonterminal_transition_map[&NonterminalTransition { state: q, var: A }];
-                            successors[i].insert(self.nonterminal_transition_map[transition]);
+                            included_by.push(self.nonterminal_transition_map[&NonterminalTransition { state: q, var: A }]);
                         }
                     }
                     q = states[q].next[&symbol];
                 }
             }
+            included_by
+        });
+
+        let mut successors = vec![BTreeSet::new(); self.nonterminal_transitions.len()];
+        for (j, included_by) in included_by.into_iter().enumerate() {
+            for i in included_by {
+                successors[i].insert(j);
+            }
         }
 
         successors
     }
 
     #[must_use]
-    pub fn lookback(&self) -> HashMap<StateReductionPair, HashSet<usize>> {
+    pub fn lookback(&self) -> BTreeMap<StateReductionPair, BTreeSet<usize>> {
         // let inconsistent_state_reduction_pairs: Vec<(usize, usize)> = self.lr0a.states().iter()
         //     .enumerate()
         //     .filter_map(|(q, state)| {
@@ -185,13 +193,19 @@ impl LALR1ABuilder<'_> {
         //     }).flatten().collect();
         // println!("{:?}", inconsistent_state_reduction_pairs);
         let states = self.lr0a.states();
-        let mut map: HashMap<StateReductionPair, HashSet<usize>> = HashMap::new();
-        for (i, &transition) in self.nonterminal_transitions().iter().enumerate() {
+        let pairs = parallel::map(self.nonterminal_transitions(), |&transition| {
             let NonterminalTransition { state: p, var: A } = transition;
             let rule = self.grammar.rules().get(A);
-            for (alt_index, alt) in rule.production_ids().zip(rule.alts()) {
+            rule.production_ids().zip(rule.alts()).map(|(alt_index, alt)| {
                 let q = alt.iter().fold(p, |q, symbol| states[q].next[symbol]);
-                map.entry(StateReductionPair { state: q, production: alt_index }).or_default().insert(i);
+                StateReductionPair { state: q, production: alt_index }
+            }).collect::<Vec<_>>()
+        });
+
+        let mut map: BTreeMap<StateReductionPair, BTreeSet<usize>> = BTreeMap::new();
+        for (i, pairs) in pairs.into_iter().enumerate() {
+            for pair in pairs {
+                map.entry(pair).or_default().insert(i);
             }
         }
         map
@@ -202,6 +216,6 @@ impl LALR1ABuilder<'_> {
 // === INTERNALS === 
 // =================
 
-fn extend(a: &mut HashSet<Option<usize>>, b: &HashSet<Option<usize>>) {
-    a.extend(b);
+fn extend(a: &mut TokenSet, b: &TokenSet) {
+    a.union_with(b);
 }
\ No newline at end of file
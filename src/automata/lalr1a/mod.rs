@@ -1,34 +1,37 @@
 #![allow(non_snake_case)]
 
-use std::iter::Copied;
-use std::collections::{hash_set, HashSet, HashMap};
-use super::{LR0A, LR0Item, LRAutomaton, DottedItem};
-use crate::grammar::{Grammar, Symbol};
-
-pub struct LALR1A<'a> {
-    lr0a: LR0A<'a>,
-    lookahead: HashMap<StateReductionPair, HashSet<Option<usize>>>,
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use super::{lr0a, LR0A, LR0Item, LRAutomaton, DottedItem};
+use crate::grammar::{Grammar, Symbol, TokenSet, Tokens};
+
+/// LALR(1) automaton. The grammar is held through `G`, which may be a
+/// reference (`&Grammar`) or an owning handle (`Grammar`, `Arc<Grammar>`).
+pub struct LALR1A<G> {
+    lr0a: LR0A<G>,
+    lookahead: BTreeMap<StateReductionPair, TokenSet>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct StateReductionPair {
     pub state: usize,
     pub production: usize,
 }
 
-impl<'a> LALR1A<'a> {
+impl<G: Borrow<Grammar>> LALR1A<G> {
     #[must_use]
-    pub fn new(grammar: &'a Grammar) -> Self {
-        LALR1ABuilder::new(grammar).build()
+    pub fn new(grammar: G) -> Self {
+        let (states, lookahead) = LALR1ABuilder::new(grammar.borrow()).build();
+        Self { lr0a: LR0A::from_states(grammar, states), lookahead }
     }
 
     #[must_use]
-    pub fn grammar(&self) -> &'a Grammar {
+    pub fn grammar(&self) -> &Grammar {
         self.lr0a.grammar()
     }
 }
 
-impl<'a> LRAutomaton<'a> for LALR1A<'_> {
+impl<'a, G: Borrow<Grammar> + 'a> LRAutomaton<'a> for LALR1A<G> {
     type ItemSet = LALR1ItemSet<'a>;
 
     fn grammar(&self) -> &Grammar {
@@ -49,7 +52,8 @@ impl<'a> LRAutomaton<'a> for LALR1A<'_> {
 }
 
 pub struct LALR1ItemSet<'a> {
-    lalr1a: &'a LALR1A<'a>,
+    grammar: &'a Grammar,
+    lookahead: &'a BTreeMap<StateReductionPair, TokenSet>,
     state: usize,
     iter: std::slice::Iter<'a, LR0Item>,
 }
@@ -58,7 +62,8 @@ impl<'a> Iterator for LALR1ItemSet<'a> {
     type Item = LALR1ItemProxy<'a>;
     fn next(&mut self) -> Option<Self::Item> {
         Some(LALR1ItemProxy {
-            lalr1a: self.lalr1a,
+            grammar: self.grammar,
+            lookahead: self.lookahead,
             state: self.state,
             item: *self.iter.next()?,
         })
@@ -66,40 +71,50 @@ impl<'a> Iterator for LALR1ItemSet<'a> {
 }
 
 impl<'a> LALR1ItemSet<'a> {
-    pub fn new(lalr1a: &'a LALR1A, state: usize) -> Self {
-        Self { lalr1a, state, iter: lalr1a.lr0a.states()[state].items.iter() }
+    pub fn new<G: Borrow<Grammar>>(lalr1a: &'a LALR1A<G>, state: usize) -> Self {
+        Self {
+            grammar: lalr1a.grammar(),
+            lookahead: &lalr1a.lookahead,
+            state,
+            iter: lalr1a.lr0a.states()[state].items.iter(),
+        }
     }
 }
 
 
 pub struct LALR1ItemProxy<'a> {
-    lalr1a: &'a LALR1A<'a>,
+    grammar: &'a Grammar,
+    lookahead: &'a BTreeMap<StateReductionPair, TokenSet>,
     state: usize,
     item: LR0Item,
 }
 
 impl<'a> DottedItem for LALR1ItemProxy<'a> {
-    type Lookaheads = Copied<hash_set::Iter<'a, Option<usize>>>;
+    type Lookaheads = Tokens<'a>;
 
     fn production(&self) -> usize {
         self.item.production
     }
+
+    fn pos(&self) -> usize {
+        self.item.pos
+    }
     
     fn is_kernel_item(&self) -> bool {
-        self.item.is_kernel_item(self.lalr1a.grammar())
+        self.item.is_kernel_item(self.grammar)
     }
     
     fn is_complete(&self) -> bool {
-        self.item.is_complete(self.lalr1a.grammar())
+        self.item.is_complete(self.grammar)
     }
     
     fn symbol_at_dot(&self) -> Option<Symbol> {
-        self.item.symbol_at_dot(self.lalr1a.grammar())
+        self.item.symbol_at_dot(self.grammar)
     }
 
     fn lookaheads(&self) -> Self::Lookaheads {
         let pair = StateReductionPair { state: self.state, production: self.item.production };
-        self.lalr1a.lookahead[&pair].iter().copied()
+        self.lookahead[&pair].iter()
     }
 }
 
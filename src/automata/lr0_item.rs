@@ -12,10 +12,10 @@ impl LR0Item {
         Self { production, pos }
     }
 
-    /// I.e. is the start rule or dot *not* at the start.
+    /// I.e. is a start rule or dot *not* at the start.
     #[must_use]
     pub fn is_kernel_item(&self, grammar: &Grammar) -> bool {
-        self.production == grammar.productions().len() - 1 || self.pos > 0
+        self.production >= grammar.start_production(0) || self.pos > 0
     }
 
     /// I.e. dot is past the end. 
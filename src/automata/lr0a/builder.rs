@@ -1,7 +1,7 @@
 #![allow(non_snake_case)]
 
 use std::collections::BTreeSet;
-use super::{inner, LR0Item, LR0A, State};
+use super::{inner, LR0Item, State};
 use crate::grammar::{Grammar, Symbol};
 
 pub struct LR0ABuilder<'a> {
@@ -9,8 +9,10 @@ pub struct LR0ABuilder<'a> {
 }
 
 impl inner::BuildItemSets<LR0Item> for LR0ABuilder<'_> {
-    fn start_item(&self) -> LR0Item {
-        LR0Item::new(self.grammar.productions().len() - 1, 0)
+    fn start_items(&self) -> Vec<LR0Item> {
+        (0..self.grammar.entry_points().len())
+            .map(|entry| LR0Item::new(self.grammar.start_production(entry), 0))
+            .collect()
     }
 
     fn advance(&self, item: &LR0Item) -> LR0Item {
@@ -56,15 +58,12 @@ impl<'a> LR0ABuilder<'a> {
     }
 
     #[must_use]
-    pub fn build(self) -> LR0A<'a> {
+    pub fn build(self) -> Vec<State> {
         let (itemsets, gotos) = <Self as inner::BuildItemSets<LR0Item>>::build(&self);
 
-        LR0A {
-            grammar: self.grammar,
-            states: itemsets.into_iter()
-                .zip(gotos)
-                .map(|(items, next)| State { items: items.into_iter().collect(), next })
-                .collect()
-        }
+        itemsets.into_iter()
+            .zip(gotos)
+            .map(|(items, next)| State { items: items.into_iter().collect(), next })
+            .collect()
     }
 }
\ No newline at end of file
@@ -1,34 +1,120 @@
 #![allow(non_snake_case)]
 
-use std::collections::HashMap;
-use super::{inner, LR0Item};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::iter::{Chain, Map, Once};
+use std::ops::Range;
+use super::{inner, LR0Item, LRAutomaton, DottedItem};
 use crate::grammar::{Grammar, Symbol};
 
-pub struct LR0A<'a> {
-    grammar: &'a Grammar,
+/// LR(0) automaton. The grammar is held through `G`, which may be a
+/// reference (`&Grammar`) or an owning handle (`Grammar`, `Arc<Grammar>`).
+///
+/// As an [`LRAutomaton`], every token is a lookahead of every item, so that
+/// tables built from it reduce regardless of the next word.
+pub struct LR0A<G> {
+    grammar: G,
     states: Vec<State>,
 }
 
 pub struct State {
-    pub next: HashMap<Symbol, usize>,
+    pub next: BTreeMap<Symbol, usize>,
     pub items: Vec<LR0Item>,
 }
 
-impl<'a> LR0A<'a> {
+impl<G: Borrow<Grammar>> LR0A<G> {
     #[must_use]
-    pub fn new(grammar: &'a Grammar) -> Self {
-        LR0ABuilder::new(grammar).build()
+    pub fn new(grammar: G) -> Self {
+        let states = LR0ABuilder::new(grammar.borrow()).build();
+        Self { grammar, states }
     }
 
     #[must_use]
-    pub fn grammar(&self) -> &'a Grammar {
-        self.grammar
+    pub fn grammar(&self) -> &Grammar {
+        self.grammar.borrow()
     }
 
     #[must_use]
     pub fn states(&self) -> &[State] {
         &self.states
     }
+
+    /// `states` must have been built from `grammar`.
+    pub(super) fn from_states(grammar: G, states: Vec<State>) -> Self {
+        Self { grammar, states }
+    }
+
+    pub(super) fn into_states(self) -> Vec<State> {
+        self.states
+    }
+}
+
+impl<'a, G: Borrow<Grammar> + 'a> LRAutomaton<'a> for LR0A<G> {
+    type ItemSet = LR0ItemSet<'a>;
+
+    fn grammar(&self) -> &Grammar {
+        self.grammar()
+    }
+
+    fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    fn items(&'a self, state: usize) -> Self::ItemSet {
+        LR0ItemSet { grammar: self.grammar(), iter: self.states[state].items.iter() }
+    }
+
+    fn transition(&self, state: usize, symbol: Symbol) -> Option<usize> {
+        self.states[state].next.get(&symbol).copied()
+    }
+}
+
+pub struct LR0ItemSet<'a> {
+    grammar: &'a Grammar,
+    iter: std::slice::Iter<'a, LR0Item>,
+}
+
+impl<'a> Iterator for LR0ItemSet<'a> {
+    type Item = LR0ItemProxy<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(LR0ItemProxy {
+            grammar: self.grammar,
+            item: *self.iter.next()?,
+        })
+    }
+}
+
+pub struct LR0ItemProxy<'a> {
+    grammar: &'a Grammar,
+    item: LR0Item,
+}
+
+impl DottedItem for LR0ItemProxy<'_> {
+    type Lookaheads = Chain<Once<Option<usize>>, Map<Range<usize>, fn(usize) -> Option<usize>>>;
+
+    fn production(&self) -> usize {
+        self.item.production
+    }
+
+    fn pos(&self) -> usize {
+        self.item.pos
+    }
+
+    fn is_kernel_item(&self) -> bool {
+        self.item.is_kernel_item(self.grammar)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.item.is_complete(self.grammar)
+    }
+
+    fn symbol_at_dot(&self) -> Option<Symbol> {
+        self.item.symbol_at_dot(self.grammar)
+    }
+
+    fn lookaheads(&self) -> Self::Lookaheads {
+        std::iter::once(None).chain((0..self.grammar.word_count()).map(Some as fn(usize) -> Option<usize>))
+    }
 }
 
 // =================
@@ -1,9 +1,10 @@
 use super::LR0Item;
+use crate::grammar::TokenSet;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LR1Item {
     pub lr0_item: LR0Item,
-    pub lookahead: Option<usize>, // class of lookahead terminal
+    pub lookaheads: TokenSet, // classes of lookahead terminals
 }
 
 impl AsRef<LR0Item> for LR1Item {
@@ -14,10 +15,10 @@ impl AsRef<LR0Item> for LR1Item {
 
 impl LR1Item {
     #[must_use]
-    pub fn new(alt: usize, pos: usize, lookahead: Option<usize>) -> Self {
+    pub fn new(alt: usize, pos: usize, lookaheads: TokenSet) -> Self {
         Self {
             lr0_item: LR0Item::new(alt, pos),
-            lookahead,
+            lookaheads,
         }
     }
-}
\ No newline at end of file
+}
@@ -1,109 +1,216 @@
 #![allow(non_snake_case)]
 
-use std::collections::BTreeSet;
-use std::iter::once;
-use super::{inner, LR1Item, LR1A, State};
-use crate::grammar::{Grammar, Symbol, Nullable, First};
+use std::collections::{BTreeMap, HashMap};
+use super::{LR0Item, LR1Item, State};
+use crate::grammar::{Grammar, Symbol, Nullable, First, TokenSet};
+use crate::parallel;
 
+/// Builds the canonical LR(1) automaton.
+///
+/// Items are grouped by their LR(0) core, each carrying a set of lookaheads,
+/// and states are identified by their kernel items alone. Closures are not
+/// recomputed per state: for each variable `A`, the builder caches which
+/// variables are reached by closing over an item with `A` at the dot, along
+/// with how their lookaheads arise (see [`VarClosure`]).
 pub struct LR1ABuilder<'a> {
     grammar: &'a Grammar,
     nullable: Nullable,
     first: First,
+    word_count: usize,
+    closures: Vec<VarClosure>,
 }
 
-impl inner::BuildItemSets<LR1Item> for LR1ABuilder<'_> {
-    fn start_item(&self) -> LR1Item {
-        LR1Item::new(self.grammar.productions().len() - 1, 0, None)
+/// Consider closing over an item `X -> a.Ab` with lookaheads `L`, and let
+/// `L' = first(bL)`. Every variable `B` reached in the process contributes
+/// items `B -> .c` whose lookaheads are `spontaneous U (L' if propagated)`.
+/// Neither component depends on `X`, `b` or `L`, so they are computed once
+/// per variable `A`.
+struct VarClosure {
+    vars: Vec<(usize, TokenSet, bool)>, // (B, spontaneous, propagated)
+}
+
+impl<'a> LR1ABuilder<'a> {
+    #[must_use]
+    pub fn new(grammar: &'a Grammar) -> Self {
+        let (first, nullable) = grammar.first_set();
+        let mut builder = LR1ABuilder {
+            grammar,
+            nullable,
+            first,
+            word_count: grammar.word_count(),
+            closures: Vec::new(),
+        };
+        builder.closures = parallel::map_range(grammar.rules().len(), |A| builder.var_closure(A));
+        builder
     }
 
-    fn advance(&self, item: &LR1Item) -> LR1Item {
-        LR1Item::new(item.lr0_item.production, item.lr0_item.pos + 1, item.lookahead)
+    #[must_use]
+    pub fn build(self) -> Vec<State> {
+        // The start states of the entry points come first, in order.
+        let start_kernels: Vec<Vec<LR1Item>> = (0..self.grammar.entry_points().len()).map(|entry| {
+            let mut lookaheads = TokenSet::new(self.word_count);
+            lookaheads.insert(None);
+            vec![LR1Item::new(self.grammar.start_production(entry), 0, lookaheads)]
+        }).collect();
+
+        let mut states: Vec<State> = Vec::new();
+
+        // Kernels we've seen so far mapped to indices in states vector.
+        let mut table: HashMap<Vec<LR1Item>, usize> = HashMap::new();
+        for (i, kernel) in start_kernels.iter().enumerate() {
+            table.insert(kernel.clone(), i);
+        }
+
+        // NOTE: The frontier holds the kernels discovered in the previous
+        //       round, in the order they were numbered. Closures and
+        //       successors of the whole frontier are computed at once (in
+        //       parallel, if enabled), and new kernels are then numbered
+        //       sequentially in frontier order and, for each kernel, in
+        //       symbol order. Hence, numbering is canonical and does not
+        //       depend on thread count.
+        let mut frontier: Vec<Vec<LR1Item>> = start_kernels;
+
+        while !frontier.is_empty() {
+            let expanded = parallel::map(&frontier, |kernel| {
+                let items = self.closure(kernel.clone());
+                let successors = self.successors(&items);
+                (items, successors)
+            });
+            let mut new_frontier = Vec::new();
+
+            for (items, successors) in expanded {
+                let mut next = BTreeMap::new();
+                for (x, successor) in successors {
+                    let j = if let Some(&index) = table.get(&successor) {
+                        index
+                    } else {
+                        let new_index = table.len();
+                        table.insert(successor.clone(), new_index);
+                        new_frontier.push(successor);
+                        new_index
+                    };
+
+                    // Record transition on x
+                    next.insert(x, j);
+                }
+
+                states.push(State { next, items });
+            }
+
+            frontier = new_frontier;
+        }
+
+        states
     }
+}
+
+// =================
+// === INTERNALS ===
+// =================
 
-    fn symbol_at_dot(&self, item: &LR1Item) -> Option<Symbol> {
-        item.lr0_item.symbol_at_dot(self.grammar)
+impl LR1ABuilder<'_> {
+    /// Groups the items by the symbol at their dot, advancing each, in
+    /// symbol order. Advancing preserves the order of items, so each new
+    /// kernel is sorted if `items` is.
+    fn successors(&self, items: &[LR1Item]) -> BTreeMap<Symbol, Vec<LR1Item>> {
+        let mut successors: BTreeMap<Symbol, Vec<LR1Item>> = BTreeMap::new();
+        for item in items {
+            if let Some(x) = item.lr0_item.symbol_at_dot(self.grammar) {
+                successors.entry(x).or_default().push(LR1Item::new(
+                    item.lr0_item.production,
+                    item.lr0_item.pos + 1,
+                    item.lookaheads.clone(),
+                ));
+            }
+        }
+        successors
     }
 
-    /// Performs the following:
-    /// * for item `i` with variable `B` at dot:
-    /// * * for production with `B` on lhs:
-    /// * * * for symbol `b` in :
-    /// * * * * add item that is the production with dot at start and lookahead `b`
-    fn closure(&self, old_items: &BTreeSet<LR1Item>) -> BTreeSet<LR1Item> {
-        let mut items     = old_items.clone();
-        let mut new_items = old_items.clone();
-        
-        let mut done = false;
-        
-        while !done {
-            done = true;
-
-            for item in &items {
-                if let Some(Symbol::Variable(var)) = item.lr0_item.symbol_at_dot(self.grammar) {
-                    match item.lr0_item.symbol_after_dot(self.grammar) {
-                        None => {
-                            for alt in self.grammar.rules().get(var).production_ids() {
-                                if new_items.insert(LR1Item::new(alt, 0, item.lookahead)) {
-                                    done = false;
-                                }
-                            }
-                        },
-                        Some(Symbol::Terminal(a)) => {
-                            for alt in self.grammar.rules().get(var).production_ids() {
-                                if new_items.insert(LR1Item::new(alt, 0, Some(a))) {
-                                    done = false;
-                                }
-                            }
-                        },
-                        Some(Symbol::Variable(A)) => {
-                            let first_A = self.first.get(A);
-
-                            if self.nullable.get(A) {
-                                for lookahead in first_A.iter().copied().map(Some).chain(once(item.lookahead)) {
-                                    for alt in self.grammar.rules().get(var).production_ids() {
-                                        if new_items.insert(LR1Item::new(alt, 0, lookahead)) {
-                                            done = false;
-                                        }
-                                    }
-                                }
-                            } else {
-                                for &lookahead in first_A {
-                                    for alt in self.grammar.rules().get(var).production_ids() {
-                                        if new_items.insert(LR1Item::new(alt, 0, Some(lookahead))) {
-                                            done = false;
-                                        }
-                                    }
-                                }
-                            } 
-                        },
-                    };
+    /// Extends a sorted kernel with its nonkernel items, using the cached
+    /// variable closures. Kernel items never share a core with nonkernel
+    /// items, so the result is sorted and has at most one item per core.
+    fn closure(&self, kernel: Vec<LR1Item>) -> Vec<LR1Item> {
+        let var_count = self.grammar.rules().len();
+        let mut var_lookaheads: Vec<Option<TokenSet>> = vec![None; var_count];
+
+        for item in &kernel {
+            if let Some(Symbol::Variable(A)) = item.lr0_item.symbol_at_dot(self.grammar) {
+                let lookaheads = self.first_of_tail(&item.lr0_item, &item.lookaheads);
+                for (B, spontaneous, propagated) in &self.closures[A].vars {
+                    let entry = var_lookaheads[*B].get_or_insert_with(|| TokenSet::new(self.word_count));
+                    entry.union_with(spontaneous);
+                    if *propagated {
+                        entry.union_with(&lookaheads);
+                    }
                 }
             }
+        }
 
-            items = new_items.clone();
+        let mut items = kernel;
+        for (B, lookaheads) in var_lookaheads.into_iter().enumerate() {
+            if let Some(lookaheads) = lookaheads {
+                for alt in self.grammar.rules().get(B).production_ids() {
+                    items.push(LR1Item::new(alt, 0, lookaheads.clone()));
+                }
+            }
         }
-    
+        items.sort_unstable();
         items
     }
-}
 
-impl<'a> LR1ABuilder<'a> {
-    #[must_use]
-    pub fn new(grammar: &'a Grammar) -> Self {
-        let (first, nullable) = grammar.first_set();
-        LR1ABuilder { grammar, nullable, first }
+    /// Computes the variables reached by closing over an item with `A` at
+    /// the dot. For each reached item `B -> .Cd`, the items of `C` receive
+    /// `first(d)`, plus whatever `B` receives if `d` is nullable.
+    fn var_closure(&self, A: usize) -> VarClosure {
+        let var_count = self.grammar.rules().len();
+        let mut reached: Vec<Option<(TokenSet, bool)>> = vec![None; var_count];
+        reached[A] = Some((TokenSet::new(self.word_count), true));
+
+        let mut queue = vec![A];
+        while let Some(B) = queue.pop() {
+            let (spontaneous_B, propagated_B) = reached[B].clone().unwrap();
+            for alt in self.grammar.rules().get(B).alts() {
+                if let Some((&Symbol::Variable(C), tail)) = alt.split_first() {
+                    let (mut spontaneous, nullable_tail) = self.first.first_of(tail, &self.nullable);
+                    let propagated = nullable_tail && propagated_B;
+                    if nullable_tail {
+                        spontaneous.union_with(&spontaneous_B);
+                    }
+
+                    let changed = match &mut reached[C] {
+                        Some((spontaneous_C, propagated_C)) => {
+                            let changed = spontaneous_C.union_with(&spontaneous) || (propagated && !*propagated_C);
+                            *propagated_C |= propagated;
+                            changed
+                        },
+                        entry @ None => {
+                            *entry = Some((spontaneous, propagated));
+                            true
+                        },
+                    };
+
+                    if changed {
+                        queue.push(C);
+                    }
+                }
+            }
+        }
+
+        VarClosure {
+            vars: reached.into_iter()
+                .enumerate()
+                .filter_map(|(B, entry)| entry.map(|(spontaneous, propagated)| (B, spontaneous, propagated)))
+                .collect(),
+        }
     }
 
-    #[must_use]
-    pub fn build(self) -> LR1A<'a> {
-        let (itemsets, gotos) = <Self as inner::BuildItemSets<LR1Item>>::build(&self);
-
-        LR1A {
-            grammar: self.grammar,
-            states: itemsets.into_iter()
-                .zip(gotos)
-                .map(|(items, next)| State { items: items.into_iter().collect(), next })
-                .collect()
+    /// For item `A -> a.Bb` with lookaheads `L`, computes `first(bL)`.
+    fn first_of_tail(&self, item: &LR0Item, lookaheads: &TokenSet) -> TokenSet {
+        let tail = &self.grammar.productions().get(item.production).1[item.pos + 1..];
+        let (mut result, nullable) = self.first.first_of(tail, &self.nullable);
+        if nullable {
+            result.union_with(lookaheads);
         }
+        result
     }
-}
\ No newline at end of file
+}
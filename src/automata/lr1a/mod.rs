@@ -1,28 +1,31 @@
 #![allow(non_snake_case)]
 
-use std::iter::{Once, once};
-use std::collections::HashMap;
-use super::{inner, LR1Item, LRAutomaton, DottedItem};
-use crate::grammar::{Grammar, Symbol};
-
-pub struct LR1A<'a> {
-    grammar: &'a Grammar,
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use super::{LR0Item, LR1Item, LRAutomaton, DottedItem};
+use crate::grammar::{Grammar, Symbol, Tokens};
+
+/// Canonical LR(1) automaton. The grammar is held through `G`, which may be
+/// a reference (`&Grammar`) or an owning handle (`Grammar`, `Arc<Grammar>`).
+pub struct LR1A<G> {
+    grammar: G,
     states: Vec<State>,
 }
 
-impl<'a> LR1A<'a> {
+impl<G: Borrow<Grammar>> LR1A<G> {
     #[must_use]
-    pub fn new(grammar: &'a Grammar) -> Self {
-        LR1ABuilder::new(grammar).build()
+    pub fn new(grammar: G) -> Self {
+        let states = LR1ABuilder::new(grammar.borrow()).build();
+        Self { grammar, states }
     }
 
     #[must_use]
-    pub fn grammar(&self) -> &'a Grammar {
-        self.grammar
+    pub fn grammar(&self) -> &Grammar {
+        self.grammar.borrow()
     }
 }
 
-impl<'a> LRAutomaton<'a> for LR1A<'a> {
+impl<'a, G: Borrow<Grammar> + 'a> LRAutomaton<'a> for LR1A<G> {
     type ItemSet = LR1ItemSet<'a>;
 
     fn grammar(&self) -> &Grammar {
@@ -52,28 +55,32 @@ impl<'a> Iterator for LR1ItemSet<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         Some(LR1ItemProxy {
             grammar: self.grammar,
-            item: *self.iter.next()?,
+            item: self.iter.next()?,
         })
     }
 }
 
 impl<'a> LR1ItemSet<'a> {
-    pub fn new(lr1a: &'a LR1A, state: usize) -> Self {
-        Self { grammar: lr1a.grammar, iter: lr1a.states[state].items.iter() }
+    pub fn new<G: Borrow<Grammar>>(lr1a: &'a LR1A<G>, state: usize) -> Self {
+        Self { grammar: lr1a.grammar(), iter: lr1a.states[state].items.iter() }
     }
 }
 
 pub struct LR1ItemProxy<'a> {
     grammar: &'a Grammar,
-    item: LR1Item,
+    item: &'a LR1Item,
 }
 
-impl DottedItem for LR1ItemProxy<'_> {
-    type Lookaheads = Once<Option<usize>>;
+impl<'a> DottedItem for LR1ItemProxy<'a> {
+    type Lookaheads = Tokens<'a>;
 
     fn production(&self) -> usize {
         self.item.lr0_item.production
     }
+
+    fn pos(&self) -> usize {
+        self.item.lr0_item.pos
+    }
     
     fn is_kernel_item(&self) -> bool {
         self.item.lr0_item.is_kernel_item(self.grammar)
@@ -88,7 +95,7 @@ impl DottedItem for LR1ItemProxy<'_> {
     }
 
     fn lookaheads(&self) -> Self::Lookaheads {
-        once(self.item.lookahead)
+        self.item.lookaheads.iter()
     }
 }
 
@@ -97,7 +104,7 @@ impl DottedItem for LR1ItemProxy<'_> {
 // =================
 
 struct State {
-    next: HashMap<Symbol, usize>,
+    next: BTreeMap<Symbol, usize>,
     items: Vec<LR1Item>,
 }
 
@@ -11,6 +11,13 @@ where
     
     fn state_count(&self) -> usize;
 
+    /// The state parsing from entry point `entry` starts in. The start
+    /// states come first, in the order of [`Grammar::entry_points`].
+    fn start_state(&self, entry: usize) -> usize {
+        assert!(entry < self.grammar().entry_points().len(), "invalid entry point {}", entry);
+        entry
+    }
+
     fn items(&'a self, state: usize) -> Self::ItemSet;
     
     fn transition(&self, state: usize, symbol: Symbol) -> Option<usize>;
@@ -29,6 +36,9 @@ pub trait DottedItem {
 
     fn production(&self) -> usize;
 
+    /// The position of the dot in the right-hand side of the production.
+    fn pos(&self) -> usize;
+
     fn is_kernel_item(&self) -> bool;
     
     fn is_complete(&self) -> bool;
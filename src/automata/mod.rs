@@ -1,3 +1,15 @@
+//! LR automata.
+//!
+//! States, transitions and lookaheads are ordered canonically, so that any
+//! artifact derived from an automaton is reproducible from run to run:
+//! * states `0..k` are the start states of the `k` entry points, in order,
+//!   and the remaining states are numbered in the order a breadth-first
+//!   traversal from them discovers them, where the transitions out of each
+//!   state are followed in [`Symbol`](crate::grammar::Symbol) order
+//!   (terminals by index, then variables by index);
+//! * the items of each state are sorted by production, then dot position;
+//! * lookaheads are iterated in ascending order, with EOF first.
+
 mod lr0_item;
 mod lr1_item;
 pub use self::{
@@ -9,10 +21,12 @@ mod lr_automaton;
 pub use self::lr_automaton::{LRAutomaton, DottedItem};
 
 mod lr0a;
+mod slr1a;
 mod lalr1a;
 mod lr1a;
 pub use self::{
     lr0a::LR0A,
+    slr1a::SLR1A,
     lalr1a::LALR1A,
     lr1a::LR1A,
 };
@@ -21,4 +35,7 @@ pub use self::{
 // === INTERNALS ===
 // =================
 
-mod inner;
\ No newline at end of file
+mod inner;
+
+#[cfg(test)]
+mod tests;
\ No newline at end of file
@@ -0,0 +1,101 @@
+#![allow(non_snake_case)]
+
+use std::borrow::Borrow;
+use super::{LR0A, LR0Item, LRAutomaton, DottedItem};
+use crate::grammar::{Grammar, Symbol, TokenSet, Tokens};
+
+/// SLR(1) automaton: the LR(0) automaton, with the follow set of its
+/// left-hand side as the lookaheads of each item. The grammar is held
+/// through `G`, which may be a reference (`&Grammar`) or an owning handle
+/// (`Grammar`, `Arc<Grammar>`).
+pub struct SLR1A<G> {
+    lr0a: LR0A<G>,
+    follows: Vec<TokenSet>,
+}
+
+impl<G: Borrow<Grammar>> SLR1A<G> {
+    /// Builds the LR(0) automaton of `grammar` and its follow sets.
+    #[must_use]
+    pub fn new(grammar: G) -> Self {
+        let (follow, _, _) = grammar.borrow().follow_set();
+        let follows = (0..grammar.borrow().rules().len()).map(|A| follow.get(A).clone()).collect();
+        Self { lr0a: LR0A::new(grammar), follows }
+    }
+
+    /// The grammar the automaton was built from.
+    #[must_use]
+    pub fn grammar(&self) -> &Grammar {
+        self.lr0a.grammar()
+    }
+}
+
+impl<'a, G: Borrow<Grammar> + 'a> LRAutomaton<'a> for SLR1A<G> {
+    type ItemSet = SLR1ItemSet<'a>;
+
+    fn grammar(&self) -> &Grammar {
+        self.grammar()
+    }
+
+    fn state_count(&self) -> usize {
+        self.lr0a.states().len()
+    }
+
+    fn items(&'a self, state: usize) -> Self::ItemSet {
+        SLR1ItemSet { grammar: self.grammar(), follows: &self.follows, iter: self.lr0a.states()[state].items.iter() }
+    }
+
+    fn transition(&self, state: usize, symbol: Symbol) -> Option<usize> {
+        self.lr0a.states()[state].next.get(&symbol).copied()
+    }
+}
+
+pub struct SLR1ItemSet<'a> {
+    grammar: &'a Grammar,
+    follows: &'a [TokenSet],
+    iter: std::slice::Iter<'a, LR0Item>,
+}
+
+impl<'a> Iterator for SLR1ItemSet<'a> {
+    type Item = SLR1ItemProxy<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(SLR1ItemProxy {
+            grammar: self.grammar,
+            follows: self.follows,
+            item: *self.iter.next()?,
+        })
+    }
+}
+
+pub struct SLR1ItemProxy<'a> {
+    grammar: &'a Grammar,
+    follows: &'a [TokenSet],
+    item: LR0Item,
+}
+
+impl<'a> DottedItem for SLR1ItemProxy<'a> {
+    type Lookaheads = Tokens<'a>;
+
+    fn production(&self) -> usize {
+        self.item.production
+    }
+
+    fn pos(&self) -> usize {
+        self.item.pos
+    }
+
+    fn is_kernel_item(&self) -> bool {
+        self.item.is_kernel_item(self.grammar)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.item.is_complete(self.grammar)
+    }
+
+    fn symbol_at_dot(&self) -> Option<Symbol> {
+        self.item.symbol_at_dot(self.grammar)
+    }
+
+    fn lookaheads(&self) -> Self::Lookaheads {
+        self.follows[self.grammar.productions().get(self.item.production).0].iter()
+    }
+}
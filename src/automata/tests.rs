@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use super::{LRAutomaton, DottedItem, LALR1A, LR1A};
+use crate::grammar::{Grammar, GrammarBuilder, Symbol, Symbol::Terminal as Word, Symbol::Variable as Var};
+
+fn lr_expr_grammar() -> Grammar {
+    // E : E + T | T,
+    // T : T * F | F,
+    // F : ( E ) | n,
+    GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Var(1)])
+                                    .add_production([Var(1)])
+                         .new_rule().add_production([Var(1), Word(1), Var(2)])
+                                    .add_production([Var(2)])
+                         .new_rule().add_production([Word(2), Var(0), Word(3)])
+                                    .add_production([Word(4)])
+                         .build().unwrap()
+}
+
+/// Checks that states are numbered in breadth-first order, following the
+/// transitions of each state in symbol order.
+fn assert_canonical_numbering<'a, A: LRAutomaton<'a>>(automaton: &A)
+where
+    <A::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    let grammar = automaton.grammar();
+    let symbols: Vec<Symbol> = (0..grammar.word_count()).map(Word)
+        .chain((0..grammar.rules().len()).map(Var))
+        .collect();
+
+    let mut seen = vec![false; automaton.state_count()];
+    let mut order = Vec::new();
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for entry in 0..grammar.entry_points().len() {
+        let start = automaton.start_state(entry);
+        seen[start] = true;
+        queue.push_back(start);
+    }
+
+    while let Some(state) = queue.pop_front() {
+        order.push(state);
+        for &symbol in &symbols {
+            if let Some(next) = automaton.transition(state, symbol) {
+                if !seen[next] {
+                    seen[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    assert_eq!(order, (0..automaton.state_count()).collect::<Vec<_>>());
+}
+
+fn describe<'a, A: LRAutomaton<'a>>(automaton: &'a A) -> Vec<(usize, Vec<Option<usize>>)>
+where
+    <A::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    let start_production = automaton.grammar().start_production(0);
+    (0..automaton.state_count())
+        .flat_map(|state| automaton.items(state))
+        .map(|item| {
+            let has_lookaheads = item.is_complete() && item.production() < start_production;
+            let lookaheads = if has_lookaheads { item.lookaheads().into_iter().collect() } else { Vec::new() };
+            (item.production(), lookaheads)
+        })
+        .collect()
+}
+
+#[test]
+fn lalr1a_numbering_is_canonical() {
+    let grammar = lr_expr_grammar();
+    let lalr1a = LALR1A::new(&grammar);
+    assert_canonical_numbering(&lalr1a);
+    assert_eq!(describe(&lalr1a), describe(&LALR1A::new(&grammar)));
+}
+
+#[test]
+fn lr1a_numbering_is_canonical() {
+    let grammar = lr_expr_grammar();
+    let lr1a = LR1A::new(&grammar);
+    assert_canonical_numbering(&lr1a);
+    assert_eq!(describe(&lr1a), describe(&LR1A::new(&grammar)));
+}
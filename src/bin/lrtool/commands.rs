@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use lr_parsing_tools::automata::{DottedItem, LRAutomaton};
+use lr_parsing_tools::grammar::{Describe, Recursion, Step, Symbol};
+use lr_parsing_tools::table::{Action, Event, LR1Table, NaiveLR1Table, Parse};
+use super::format::GrammarFile;
+
+/// The outcome of a command: whether it found nothing to complain about.
+pub type Outcome = Result<bool, String>;
+
+/// `check`: reports hygiene problems and problematic recursion.
+pub fn check(file: &GrammarFile, out: &mut String) -> Outcome {
+    let grammar = &file.grammar;
+    let entry_names: Vec<String> = grammar.entry_points().iter().map(|&A| file.variable(A)).collect();
+    writeln!(out, "{} terminals, {} variables, {} productions, entry points: {}",
+        file.terminals.len(), file.variables.len(), grammar.start_production(0), entry_names.join(", ")).unwrap();
+
+    let mut issues = 0;
+    let hygiene = grammar.hygiene();
+    for &A in hygiene.unproductive() {
+        writeln!(out, "unproductive variable: {}", file.variable(A)).unwrap();
+        issues += 1;
+    }
+    for &symbol in hygiene.unreachable() {
+        writeln!(out, "unreachable symbol: {}", file.symbol(symbol)).unwrap();
+        issues += 1;
+    }
+    for &a in hygiene.unused_terminals() {
+        writeln!(out, "unused terminal: {}", file.token(Some(a))).unwrap();
+        issues += 1;
+    }
+    for &(j, i) in hygiene.duplicates() {
+        writeln!(out, "duplicate production: {} (production {} repeats {})", file.production(j), j, i).unwrap();
+        issues += 1;
+    }
+    for recursion in grammar.recursion() {
+        let kind = match recursion {
+            Recursion::Cycle { .. } => "derivation cycle",
+            Recursion::HiddenLeftRecursion { .. } => "hidden left recursion",
+        };
+        writeln!(out, "{} through {}:", kind, file.variable(recursion.var())).unwrap();
+        write_steps(file, recursion.chain(), out);
+        issues += 1;
+    }
+
+    if issues == 0 {
+        writeln!(out, "ok").unwrap();
+    } else {
+        writeln!(out, "{} issue{}", issues, if issues == 1 { "" } else { "s" }).unwrap();
+    }
+    Ok(issues == 0)
+}
+
+/// `sets`: nullability, first and follow sets of every variable.
+pub fn sets(file: &GrammarFile, out: &mut String) -> Outcome {
+    let (follow, first, nullable) = file.grammar.follow_set();
+    for A in 0..file.variables.len() {
+        writeln!(out, "{}", file.variable(A)).unwrap();
+        writeln!(out, "  nullable: {}", if nullable.get(A) { "yes" } else { "no" }).unwrap();
+        writeln!(out, "  first:    {}", tokens(file, first.get(A).iter())).unwrap();
+        writeln!(out, "  follow:   {}", tokens(file, follow.get(A).iter())).unwrap();
+    }
+    Ok(true)
+}
+
+/// `automaton`: lists the states with their items and transitions.
+pub fn automaton<'a, A>(file: &GrammarFile, automaton: &'a A, lookaheads: bool, out: &mut String) -> Outcome
+where
+    A: LRAutomaton<'a>,
+    <A::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    for state in 0..automaton.state_count() {
+        writeln!(out, "state {}", state).unwrap();
+        for item in automaton.items(state) {
+            write!(out, "  {}", file.item(item.production(), item.pos())).unwrap();
+            // Augmented start items accept rather than reduce, and have no
+            // lookaheads of their own.
+            let augmented = file.grammar.productions().get(item.production()).0 >= file.variables.len();
+            if lookaheads && item.is_complete() && !augmented {
+                write!(out, "  [{}]", tokens(file, item.lookaheads().into_iter())).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        for (symbol, next) in transitions(file, automaton, state) {
+            writeln!(out, "  on {} goto {}", file.symbol(symbol), next).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    Ok(true)
+}
+
+/// `conflicts`: every conflict of every state, with a shortest way of
+/// reaching the state and why the lookahead may follow.
+pub fn conflicts<'a, A>(file: &GrammarFile, automaton: &'a A, out: &mut String) -> Outcome
+where
+    A: LRAutomaton<'a>,
+    <A::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    let grammar = &file.grammar;
+    let paths = shortest_paths(file, automaton);
+    let mut count = 0;
+
+    for (state, path) in paths.iter().enumerate() {
+        // For each lookahead, the items shifting it and the items reducing
+        // on it.
+        let mut columns: BTreeMap<Option<usize>, Column> = BTreeMap::new();
+        for item in automaton.items(state) {
+            match item.symbol_at_dot() {
+                Some(Symbol::Terminal(a)) => columns.entry(Some(a)).or_default().shifts.push((item.production(), item.pos())),
+                Some(Symbol::Variable(_)) => {},
+                None if grammar.productions().get(item.production()).0 < grammar.start_var(0) => {
+                    for token in item.lookaheads() {
+                        columns.entry(token).or_default().reduces.push(item.production());
+                    }
+                },
+                None => {},
+            }
+        }
+
+        for (token, Column { shifts, reduces }) in columns {
+            if reduces.is_empty() || (shifts.is_empty() && reduces.len() == 1) {
+                continue;
+            }
+            count += 1;
+            let kind = if shifts.is_empty() { "reduce/reduce" } else { "shift/reduce" };
+            writeln!(out, "state {}: {} conflict on {}", state, kind, file.token(token)).unwrap();
+            for &(production, pos) in &shifts {
+                writeln!(out, "  shift:  {}", file.item(production, pos)).unwrap();
+            }
+            for &production in &reduces {
+                writeln!(out, "  reduce: {}", file.item(production, grammar.productions().get(production).1.len())).unwrap();
+            }
+            writeln!(out, "  reached by: {}", path.iter().map(|&symbol| file.symbol(symbol)).collect::<Vec<_>>().join(" ")).unwrap();
+            for &production in &reduces {
+                let A = grammar.productions().get(production).0;
+                if let Some(explanation) = grammar.explain_follow(A, token) {
+                    writeln!(out, "  {} may follow {}:", file.token(token), file.variable(A)).unwrap();
+                    write_steps(file, explanation.occurrences(), out);
+                    write_steps(file, explanation.first(), out);
+                }
+            }
+            writeln!(out).unwrap();
+        }
+    }
+
+    writeln!(out, "{} conflict{}", count, if count == 1 { "" } else { "s" }).unwrap();
+    Ok(count == 0)
+}
+
+/// `dot`: the automaton in Graphviz format.
+pub fn dot<'a, A>(file: &GrammarFile, automaton: &'a A, out: &mut String) -> Outcome
+where
+    A: LRAutomaton<'a>,
+    <A::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    writeln!(out, "digraph automaton {{").unwrap();
+    writeln!(out, "    rankdir=LR;").unwrap();
+    writeln!(out, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+    for state in 0..automaton.state_count() {
+        let mut label = format!("{}\\n", state);
+        for item in automaton.items(state).into_iter().filter(|item| item.is_kernel_item()) {
+            label.push_str(&escape(&file.item(item.production(), item.pos())));
+            label.push_str("\\l");
+        }
+        writeln!(out, "    {} [label=\"{}\"];", state, label).unwrap();
+    }
+    for state in 0..automaton.state_count() {
+        for (symbol, next) in transitions(file, automaton, state) {
+            writeln!(out, "    {} -> {} [label=\"{}\"];", state, next, escape(&file.symbol(symbol))).unwrap();
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    Ok(true)
+}
+
+/// `table`: the action and goto tables, or their statistics only.
+pub fn table(file: &GrammarFile, table: &NaiveLR1Table, stats: bool, out: &mut String) -> Outcome {
+    let word_count = file.terminals.len();
+    let var_count = file.variables.len();
+    let actions: Vec<Vec<Action>> = (0..table.state_count())
+        .map(|state| (0..=word_count).map(|column| table.action(state, column.checked_sub(1))).collect())
+        .collect();
+
+    if stats {
+        let action_count = actions.iter().flatten().filter(|action| !matches!(action, Action::Invalid)).count();
+        let goto_count = (0..table.state_count())
+            .map(|state| (0..var_count).filter(|&A| table.goto(state, A).is_some()).count())
+            .sum::<usize>();
+        writeln!(out, "states:  {}", table.state_count()).unwrap();
+        writeln!(out, "actions: {} of {}", action_count, table.state_count() * (word_count + 1)).unwrap();
+        writeln!(out, "gotos:   {} of {}", goto_count, table.state_count() * var_count).unwrap();
+        return Ok(true);
+    }
+
+    let mut rows = vec![
+        std::iter::once("state".to_string())
+            .chain((0..=word_count).map(|column| file.token(column.checked_sub(1))))
+            .chain((0..var_count).map(|A| file.variable(A)))
+            .collect::<Vec<_>>(),
+    ];
+    for (state, row) in actions.iter().enumerate() {
+        rows.push(std::iter::once(state.to_string())
+            .chain(row.iter().map(|action| match action {
+                Action::Invalid => String::new(),
+                Action::Accept => "acc".to_string(),
+                Action::Shift(next) => format!("s{}", next),
+                Action::Reduce(production) => format!("r{}", production),
+            }))
+            .chain((0..var_count).map(|A| table.goto(state, A).map_or_else(String::new, |next| next.to_string())))
+            .collect());
+    }
+    write_columns(&rows, out);
+    Ok(true)
+}
+
+/// `parse`: runs the table on `input`, listing the events, or every step if
+/// `trace` is set.
+pub fn parse(file: &GrammarFile, table: &NaiveLR1Table, entry: usize, input: &[usize], trace: bool, out: &mut String) -> Outcome {
+    let parse = Parse::new(table, entry, input.iter().copied().map(Ok::<_, ()>), |a: &usize| *a);
+    let mut parse = if trace { parse.traced() } else { parse };
+    let mut accepted = true;
+    let mut events = String::new();
+    for event in &mut parse {
+        match event {
+            Ok(Event::Shift(a)) => writeln!(events, "shift  {}", file.token(Some(a))).unwrap(),
+            Ok(Event::Reduce { production, .. }) => writeln!(events, "reduce {}", file.production(production)).unwrap(),
+            Err(err) => {
+                let message = err.named(&file.names);
+                writeln!(events, "error: {}", message).unwrap();
+                accepted = false;
+                break;
+            },
+        }
+    }
+
+    if trace {
+        out.push_str(&parse.trace().unwrap().render(|token| file.token(token)));
+    } else {
+        out.push_str(&events);
+    }
+    if accepted {
+        writeln!(out, "accepted").unwrap();
+    } else if trace {
+        out.push_str(events.lines().last().unwrap());
+        out.push('\n');
+    }
+    Ok(accepted)
+}
+
+/// `gen`: Rust source defining the table as constants.
+pub fn gen(file: &GrammarFile, table: &NaiveLR1Table, out: &mut String) -> Outcome {
+    let grammar = &file.grammar;
+    let quote = |names: &[String]| names.iter().map(|name| format!("{:?}", name)).collect::<Vec<_>>().join(", ");
+
+    writeln!(out, "// Generated by lrtool. Do not edit.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "/// Terminal names, by index.").unwrap();
+    writeln!(out, "pub const TERMINALS: &[&str] = &[{}];", quote(&file.terminals)).unwrap();
+    writeln!(out, "/// Variable names, by index.").unwrap();
+    writeln!(out, "pub const VARIABLES: &[&str] = &[{}];", quote(&file.variables)).unwrap();
+    writeln!(out, "/// The start state of each entry point.").unwrap();
+    writeln!(out, "pub const START_STATES: &[u32] = &{:?};",
+        (0..grammar.entry_points().len()).map(|entry| table.start_state(entry)).collect::<Vec<_>>()).unwrap();
+    writeln!(out, "/// For each production, its left-hand side and length.").unwrap();
+    writeln!(out, "pub const REDUCTIONS: &[(u32, u32)] = &{:?};",
+        grammar.productions().into_iter().map(|(A, alt)| (A, alt.len())).collect::<Vec<_>>()).unwrap();
+    writeln!(out, "/// One row per state, one column per terminal with EOF first: 0 is an").unwrap();
+    writeln!(out, "/// error, 1 accepts, `2 + 2 * s` shifts to state `s`, `3 + 2 * p` reduces").unwrap();
+    writeln!(out, "/// production `p`.").unwrap();
+    writeln!(out, "pub const ACTIONS: &[u32] = &[").unwrap();
+    for state in 0..table.state_count() {
+        let row: Vec<String> = (0..=file.terminals.len())
+            .map(|column| match table.action(state, column.checked_sub(1)) {
+                Action::Invalid => 0,
+                Action::Accept => 1,
+                Action::Shift(next) => 2 + 2 * next,
+                Action::Reduce(production) => 3 + 2 * production,
+            }.to_string())
+            .collect();
+        writeln!(out, "    {},", row.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out, "/// One row per state, one column per variable: the state to go to, or").unwrap();
+    writeln!(out, "/// `u32::MAX` if none.").unwrap();
+    writeln!(out, "pub const GOTOS: &[u32] = &[").unwrap();
+    for state in 0..table.state_count() {
+        let row: Vec<String> = (0..file.variables.len())
+            .map(|A| table.goto(state, A).map_or_else(|| "u32::MAX".to_string(), |next| next.to_string()))
+            .collect();
+        writeln!(out, "    {},", row.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    Ok(true)
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// The items of a state shifting or reducing on one lookahead.
+#[derive(Default)]
+struct Column {
+    shifts: Vec<(usize, usize)>, // (production, pos)
+    reduces: Vec<usize>,
+}
+
+fn tokens<I: Iterator<Item = Option<usize>>>(file: &GrammarFile, tokens: I) -> String {
+    let names: Vec<String> = tokens.map(|token| file.token(token)).collect();
+    if names.is_empty() { "∅".to_string() } else { names.join(" ") }
+}
+
+fn write_steps(file: &GrammarFile, steps: &[Step], out: &mut String) {
+    for step in steps {
+        writeln!(out, "    {}", file.marked(step.production, step.pos)).unwrap();
+    }
+}
+
+/// The transitions out of `state`, terminals first.
+fn transitions<'a, A>(file: &GrammarFile, automaton: &'a A, state: usize) -> Vec<(Symbol, usize)>
+where
+    A: LRAutomaton<'a>,
+    <A::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    (0..file.terminals.len()).map(Symbol::Terminal)
+        .chain((0..file.variables.len()).map(Symbol::Variable))
+        .filter_map(|symbol| automaton.transition(state, symbol).map(|next| (symbol, next)))
+        .collect()
+}
+
+/// For each state, a shortest sequence of symbols leading to it from a start
+/// state.
+fn shortest_paths<'a, A>(file: &GrammarFile, automaton: &'a A) -> Vec<Vec<Symbol>>
+where
+    A: LRAutomaton<'a>,
+    <A::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    let mut paths: Vec<Option<Vec<Symbol>>> = vec![None; automaton.state_count()];
+    let mut queue = VecDeque::new();
+    for entry in 0..file.grammar.entry_points().len() {
+        let state = automaton.start_state(entry);
+        paths[state] = Some(Vec::new());
+        queue.push_back(state);
+    }
+    while let Some(state) = queue.pop_front() {
+        for (symbol, next) in transitions(file, automaton, state) {
+            if paths[next].is_none() {
+                let mut path = paths[state].clone().unwrap();
+                path.push(symbol);
+                paths[next] = Some(path);
+                queue.push_back(next);
+            }
+        }
+    }
+    paths.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn write_columns(rows: &[Vec<String>], out: &mut String) {
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+    for row in rows {
+        let cells: Vec<String> = row.iter().zip(&widths)
+            .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+            .collect();
+        writeln!(out, "{}", cells.join(" | ").trim_end()).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+//! The grammar file format.
+//!
+//! ```text
+//! # Comments run to the end of the line.
+//! %start Program Expr;     # entry points, the first rule by default
+//! %token num name;         # fixes the numbering of these terminals first
+//!
+//! Program : Program Stmt
+//!         |                # an empty alternative, also written %empty
+//!         ;
+//! Stmt : Expr ';' ;
+//! Expr : Expr '+' num | num ;
+//! ```
+//!
+//! Names defined by a rule are variables, numbered in order of definition.
+//! Every other name, and every quoted string, is a terminal, numbered in
+//! order of first appearance after those listed by `%token`. A rule may be
+//! defined in several parts, whose alternatives are concatenated.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use lr_parsing_tools::grammar::{Alphabet, Grammar, GrammarBuilder, Names, Symbol};
+
+/// A grammar along with the names of its symbols.
+pub struct GrammarFile {
+    pub grammar: Grammar,
+    pub terminals: Vec<String>,
+    pub variables: Vec<String>,
+    /// The names, for messages of the library.
+    pub names: Names,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct FormatError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for FormatError {}
+
+pub fn parse(source: &str) -> Result<GrammarFile, FormatError> {
+    let tokens = lex(source)?;
+    let statements = parse_statements(&tokens)?;
+
+    // Variables are the names defined by rules, in order of definition.
+    let mut variables: Vec<String> = Vec::new();
+    let mut variable_ids: HashMap<&str, usize> = HashMap::new();
+    for statement in &statements {
+        if let Statement::Rule { name, .. } = statement {
+            if !variable_ids.contains_key(name.text.as_str()) {
+                variable_ids.insert(&name.text, variables.len());
+                variables.push(name.text.clone());
+            }
+        }
+    }
+    if variables.is_empty() {
+        return Err(FormatError { line: 1, column: 1, message: "the grammar has no rules".to_string() });
+    }
+
+    let mut terminals: Vec<String> = Vec::new();
+    let mut terminal_ids: HashMap<String, usize> = HashMap::new();
+    let mut terminal = |token: &Token| -> usize {
+        let key = token.unquoted().to_string();
+        *terminal_ids.entry(key).or_insert_with(|| {
+            terminals.push(token.text.clone());
+            terminals.len() - 1
+        })
+    };
+
+    for statement in &statements {
+        if let Statement::Tokens(names) = statement {
+            for name in names {
+                terminal(name);
+            }
+        }
+    }
+
+    let mut alternatives: Vec<Vec<Vec<Symbol>>> = vec![Vec::new(); variables.len()];
+    let mut entry_points = Vec::new();
+    for statement in &statements {
+        match statement {
+            Statement::Rule { name, alts } => {
+                let A = variable_ids[name.text.as_str()];
+                for alt in alts {
+                    alternatives[A].push(alt.iter()
+                        .map(|token| match variable_ids.get(token.text.as_str()) {
+                            Some(&B) if token.kind == TokenKind::Name => Symbol::Variable(B),
+                            _ => Symbol::Terminal(terminal(token)),
+                        })
+                        .collect());
+                }
+            },
+            Statement::Start(names) => for name in names {
+                match variable_ids.get(name.text.as_str()) {
+                    Some(&A) if name.kind == TokenKind::Name => entry_points.push(A),
+                    _ => return Err(name.error(format!("`{}` is not defined by a rule", name.text))),
+                }
+            },
+            Statement::Tokens(_) => {},
+        }
+    }
+    if entry_points.is_empty() {
+        entry_points.push(0);
+    }
+
+    let mut alphabet = Alphabet::new();
+    for terminal in &terminals {
+        alphabet.add(terminal);
+    }
+    let mut builder = GrammarBuilder::new().alphabet(alphabet);
+    for alts in alternatives {
+        builder = builder.new_rule();
+        for alt in alts {
+            builder = builder.add_production(alt);
+        }
+    }
+    let grammar = builder.entry_points(entry_points).build()
+        .map_err(|err| FormatError { line: 1, column: 1, message: format!("invalid grammar: {}", err) })?;
+
+    let augmented = grammar.entry_points().iter().map(|&A| format!("{}'", variables[A]));
+    let names = Names::new(terminals.clone(), variables.iter().cloned().chain(augmented)).with_grammar(&grammar);
+    Ok(GrammarFile { grammar, terminals, variables, names })
+}
+
+impl GrammarFile {
+    /// The name of a symbol. Augmented start variables are named after their
+    /// entry point, with a prime.
+    pub fn symbol(&self, symbol: Symbol) -> String {
+        match symbol {
+            Symbol::Terminal(a) => self.terminals[a].clone(),
+            Symbol::Variable(A) => self.variable(A),
+        }
+    }
+
+    pub fn variable(&self, var: usize) -> String {
+        match self.variables.get(var) {
+            Some(name) => name.clone(),
+            None => {
+                let entry = var - self.variables.len();
+                format!("{}'", self.variables[self.grammar.entry_points()[entry]])
+            },
+        }
+    }
+
+    /// The name of a token, `$` for EOF.
+    pub fn token(&self, token: Option<usize>) -> String {
+        token.map_or_else(|| "$".to_string(), |a| self.terminals[a].clone())
+    }
+
+    /// `A -> x y`, or `A -> ε` for an empty production.
+    pub fn production(&self, production: usize) -> String {
+        self.dotted(production, None, "")
+    }
+
+    /// `A -> x . y`.
+    pub fn item(&self, production: usize, pos: usize) -> String {
+        self.dotted(production, Some(pos), ".")
+    }
+
+    /// `A -> x [y]`, marking the symbol at `pos`.
+    pub fn marked(&self, production: usize, pos: usize) -> String {
+        let (A, alt) = self.grammar.productions().get(production);
+        let symbols: Vec<String> = alt.iter().enumerate()
+            .map(|(i, &symbol)| if i == pos { format!("[{}]", self.symbol(symbol)) } else { self.symbol(symbol) })
+            .collect();
+        format!("{} -> {}", self.variable(A), symbols.join(" "))
+    }
+
+    fn dotted(&self, production: usize, dot: Option<usize>, mark: &str) -> String {
+        let (A, alt) = self.grammar.productions().get(production);
+        let mut symbols: Vec<String> = alt.iter().map(|&symbol| self.symbol(symbol)).collect();
+        if let Some(pos) = dot {
+            symbols.insert(pos, mark.to_string());
+        }
+        if symbols.is_empty() {
+            symbols.push("ε".to_string());
+        }
+        format!("{} -> {}", self.variable(A), symbols.join(" "))
+    }
+
+    /// Looks up a terminal by name, with or without quotes.
+    pub fn terminal_id(&self, name: &str) -> Option<usize> {
+        self.terminals.iter().position(|terminal| terminal == name || unquote(terminal) == name)
+    }
+
+    pub fn variable_id(&self, name: &str) -> Option<usize> {
+        self.variables.iter().position(|variable| variable == name)
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Name,
+    Quoted,
+    Colon,
+    Pipe,
+    Semicolon,
+    Start,
+    Token,
+    Empty,
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn unquoted(&self) -> &str {
+        unquote(&self.text)
+    }
+
+    fn error(&self, message: String) -> FormatError {
+        FormatError { line: self.line, column: self.column, message }
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    let quoted = text.len() >= 2
+        && (text.starts_with('\'') && text.ends_with('\'') || text.starts_with('"') && text.ends_with('"'));
+    if quoted { &text[1..text.len() - 1] } else { text }
+}
+
+enum Statement<'a> {
+    Rule { name: &'a Token, alts: Vec<Vec<&'a Token>> },
+    Start(Vec<&'a Token>),
+    Tokens(Vec<&'a Token>),
+}
+
+fn lex(source: &str) -> Result<Vec<Token>, FormatError> {
+    let mut tokens = Vec::new();
+    for (line, text) in source.lines().enumerate() {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let (start, c) = chars[i];
+            let column = text[..start].chars().count() + 1;
+            let token = |kind, end: usize| Token { kind, text: text[start..end].to_string(), line: line + 1, column };
+            let end_of = |i: usize| chars.get(i).map_or(text.len(), |&(offset, _)| offset);
+
+            match c {
+                '#' => break,
+                c if c.is_whitespace() => i += 1,
+                ':' => { tokens.push(token(TokenKind::Colon, end_of(i + 1))); i += 1; },
+                '|' => { tokens.push(token(TokenKind::Pipe, end_of(i + 1))); i += 1; },
+                ';' => { tokens.push(token(TokenKind::Semicolon, end_of(i + 1))); i += 1; },
+                '\'' | '"' => {
+                    let close = (i + 1..chars.len()).find(|&j| chars[j].1 == c)
+                        .ok_or(FormatError { line: line + 1, column, message: "unterminated string".to_string() })?;
+                    if close == i + 1 {
+                        return Err(FormatError { line: line + 1, column, message: "empty string".to_string() });
+                    }
+                    tokens.push(token(TokenKind::Quoted, end_of(close + 1)));
+                    i = close + 1;
+                },
+                '%' => {
+                    let end = (i + 1..chars.len()).find(|&j| !is_name_char(chars[j].1)).unwrap_or(chars.len());
+                    let word = &text[start..end_of(end)];
+                    let kind = match word {
+                        "%start" => TokenKind::Start,
+                        "%token" => TokenKind::Token,
+                        "%empty" => TokenKind::Empty,
+                        _ => return Err(FormatError { line: line + 1, column, message: format!("unknown directive `{}`", word) }),
+                    };
+                    tokens.push(token(kind, end_of(end)));
+                    i = end;
+                },
+                c if c.is_alphanumeric() || c == '_' => {
+                    let end = (i + 1..chars.len()).find(|&j| !is_name_char(chars[j].1)).unwrap_or(chars.len());
+                    tokens.push(token(TokenKind::Name, end_of(end)));
+                    i = end;
+                },
+                c => return Err(FormatError { line: line + 1, column, message: format!("unexpected character `{}`", c) }),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+fn parse_statements(tokens: &[Token]) -> Result<Vec<Statement<'_>>, FormatError> {
+    let mut statements = Vec::new();
+    let mut i = 0;
+    let end_error = || FormatError {
+        line: tokens.last().map_or(1, |token| token.line),
+        column: tokens.last().map_or(1, |token| token.column),
+        message: "missing `;` at end of file".to_string(),
+    };
+
+    while i < tokens.len() {
+        let head = &tokens[i];
+        match head.kind {
+            TokenKind::Start | TokenKind::Token => {
+                let mut names = Vec::new();
+                i += 1;
+                loop {
+                    let token = tokens.get(i).ok_or_else(end_error)?;
+                    i += 1;
+                    match token.kind {
+                        TokenKind::Semicolon => break,
+                        TokenKind::Name => names.push(token),
+                        TokenKind::Quoted if head.kind == TokenKind::Token => names.push(token),
+                        _ => return Err(token.error(format!("unexpected `{}` in directive", token.text))),
+                    }
+                }
+                statements.push(if head.kind == TokenKind::Start { Statement::Start(names) } else { Statement::Tokens(names) });
+            },
+            TokenKind::Name => {
+                match tokens.get(i + 1) {
+                    Some(token) if token.kind == TokenKind::Colon => {},
+                    Some(token) => return Err(token.error(format!("expected `:` after `{}`", head.text))),
+                    None => return Err(end_error()),
+                }
+                i += 2;
+                let mut alts = vec![Vec::new()];
+                loop {
+                    let token = tokens.get(i).ok_or_else(end_error)?;
+                    i += 1;
+                    match token.kind {
+                        TokenKind::Semicolon => break,
+                        TokenKind::Pipe => alts.push(Vec::new()),
+                        TokenKind::Name | TokenKind::Quoted => alts.last_mut().unwrap().push(token),
+                        TokenKind::Empty => {},
+                        _ => return Err(token.error(format!("unexpected `{}` in rule", token.text))),
+                    }
+                }
+                statements.push(Statement::Rule { name: head, alts });
+            },
+            _ => return Err(head.error(format!("expected a rule or directive, found `{}`", head.text))),
+        }
+    }
+
+    Ok(statements)
+}
@@ -0,0 +1,173 @@
+//! `lrtool`: grammar analysis from the command line.
+//!
+//! Reads a grammar file in the format described in [`format`], and runs one
+//! of the subcommands listed in [`USAGE`] on it. Exits with 0 on success, 1
+//! when the command finds problems (issues, conflicts, rejected input), and
+//! 2 on invalid usage or input.
+
+#![allow(non_snake_case)]
+
+use std::process;
+use lr_parsing_tools::automata::{LR0A, SLR1A, LALR1A, LR1A};
+use lr_parsing_tools::grammar::Describe;
+use lr_parsing_tools::table::{AutomatonKind, NaiveLR1Table};
+
+mod commands;
+mod format;
+
+const USAGE: &str = "\
+usage: lrtool <command> [options] <grammar file> [tokens...]
+
+commands:
+    check        validate the grammar and report hygiene problems
+    sets         list nullability, first and follow sets
+    automaton    list the states of the automaton
+    conflicts    list the conflicts of the automaton, with explanations
+    dot          print the automaton in Graphviz format
+    table        print the parse table
+    parse        parse the given tokens, listing shifts and reductions
+    gen          print the parse table as Rust source
+
+options:
+    --kind lr0|slr|lalr|lr1    the automaton to build (default: lalr)
+    --entry <variable>         the entry point to parse from (default: the first)
+    --stats                    with `table`, print statistics only
+    --trace                    with `parse`, print the stack and action of every step
+";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = match run(&args) {
+        Ok((output, ok)) => {
+            print!("{}", output);
+            if ok { 0 } else { 1 }
+        },
+        Err(message) => {
+            eprintln!("lrtool: {}", message);
+            2
+        },
+    };
+    process::exit(code);
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// Command line options.
+struct Options {
+    command: String,
+    path: String,
+    kind: AutomatonKind,
+    entry: Option<String>,
+    stats: bool,
+    trace: bool,
+    tokens: Vec<String>,
+}
+
+/// Runs the command line `args`, returning the output and whether the command
+/// succeeded, or an error message.
+fn run(args: &[String]) -> Result<(String, bool), String> {
+    let options = parse_args(args)?;
+    let source = std::fs::read_to_string(&options.path)
+        .map_err(|err| format!("cannot read {}: {}", options.path, err))?;
+    run_on_source(&options, &source)
+}
+
+fn run_on_source(options: &Options, source: &str) -> Result<(String, bool), String> {
+    let file = format::parse(source).map_err(|err| format!("{}:{}", options.path, err))?;
+    let grammar = &file.grammar;
+    let mut out = String::new();
+
+    macro_rules! with_automaton {
+        (|$automaton:ident| $body:expr) => {
+            match options.kind {
+                AutomatonKind::LR0 => { let $automaton = LR0A::new(grammar); $body },
+                AutomatonKind::SLR1 => { let $automaton = SLR1A::new(grammar); $body },
+                AutomatonKind::LALR1 => { let $automaton = LALR1A::new(grammar); $body },
+                AutomatonKind::LR1 => { let $automaton = LR1A::new(grammar); $body },
+            }
+        };
+    }
+
+    let build_table = || -> Result<NaiveLR1Table, String> {
+        with_automaton!(|automaton| NaiveLR1Table::build(&automaton, Err))
+            .map_err(|err| format!("{}; see `lrtool conflicts`", err.named(&file.names)))
+    };
+
+    let ok = match options.command.as_str() {
+        "check" => commands::check(&file, &mut out)?,
+        "sets" => commands::sets(&file, &mut out)?,
+        "automaton" => {
+            let lookaheads = options.kind != AutomatonKind::LR0;
+            with_automaton!(|automaton| commands::automaton(&file, &automaton, lookaheads, &mut out))?
+        },
+        "conflicts" => with_automaton!(|automaton| commands::conflicts(&file, &automaton, &mut out))?,
+        "dot" => with_automaton!(|automaton| commands::dot(&file, &automaton, &mut out))?,
+        "table" => commands::table(&file, &build_table()?, options.stats, &mut out)?,
+        "parse" => {
+            let entry = match &options.entry {
+                Some(name) => {
+                    let var = file.variable_id(name).ok_or_else(|| format!("unknown variable `{}`", name))?;
+                    grammar.entry_points().iter().position(|&A| A == var)
+                        .ok_or_else(|| format!("`{}` is not an entry point", name))?
+                },
+                None => 0,
+            };
+            let input = options.tokens.iter()
+                .map(|token| file.terminal_id(token).ok_or_else(|| format!("unknown terminal `{}`", token)))
+                .collect::<Result<Vec<_>, _>>()?;
+            commands::parse(&file, &build_table()?, entry, &input, options.trace, &mut out)?
+        },
+        "gen" => commands::gen(&file, &build_table()?, &mut out)?,
+        command => return Err(format!("unknown command `{}`\n{}", command, USAGE)),
+    };
+
+    Ok((out, ok))
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut positional = Vec::new();
+    let mut kind = AutomatonKind::LALR1;
+    let mut entry = None;
+    let mut stats = false;
+    let mut trace = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--kind" => {
+                kind = match args.next().map(String::as_str) {
+                    Some("lr0") => AutomatonKind::LR0,
+                    Some("slr") => AutomatonKind::SLR1,
+                    Some("lalr") => AutomatonKind::LALR1,
+                    Some("lr1") => AutomatonKind::LR1,
+                    Some(other) => return Err(format!("unknown automaton kind `{}`", other)),
+                    None => return Err("missing automaton kind".to_string()),
+                };
+            },
+            "--entry" => entry = Some(args.next().ok_or("missing entry point")?.clone()),
+            "--stats" => stats = true,
+            "--trace" => trace = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if positional.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+    let mut positional = positional.into_iter();
+    Ok(Options {
+        command: positional.next().unwrap(),
+        path: positional.next().unwrap(),
+        kind,
+        entry,
+        stats,
+        trace,
+        tokens: positional.collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests;
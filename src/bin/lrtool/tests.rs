@@ -0,0 +1,184 @@
+use lr_parsing_tools::grammar::Symbol::{Terminal as Word, Variable as Var};
+use lr_parsing_tools::table::AutomatonKind;
+use super::{format, parse_args, run_on_source, Options};
+
+const EXPR: &str = "
+    # Sums and products.
+    %token num;
+    Expr : Expr '+' Term | Term ;
+    Term : Term '*' num | num | '(' Expr ')' ;
+";
+
+/// `S -> L = R` is SLR(1)-ambiguous, but LALR(1).
+const ASSIGN: &str = "
+    S : L '=' R | R ;
+    L : '*' R | id ;
+    R : L ;
+";
+
+const DANGLING_ELSE: &str = "
+    Stmt : if cond Stmt | if cond Stmt else Stmt | other ;
+";
+
+fn options(command: &str, kind: AutomatonKind) -> Options {
+    Options {
+        command: command.to_string(),
+        path: "test.y".to_string(),
+        kind,
+        entry: None,
+        stats: false,
+        trace: false,
+        tokens: Vec::new(),
+    }
+}
+
+fn run(command: &str, source: &str) -> (String, bool) {
+    run_on_source(&options(command, AutomatonKind::LALR1), source).unwrap()
+}
+
+#[test]
+fn format_parsing() {
+    let file = format::parse(EXPR).unwrap();
+    assert_eq!(file.variables, vec!["Expr", "Term"]);
+    assert_eq!(file.terminals, vec!["num", "'+'", "'*'", "'('", "')'"]);
+    assert_eq!(file.grammar.productions().get(0), (0, &[Var(0), Word(1), Var(1)][..]));
+    assert_eq!(file.grammar.productions().get(4), (1, &[Word(3), Var(0), Word(4)][..]));
+    assert_eq!(file.terminal_id("+"), Some(1));
+    assert_eq!(file.terminal_id("'+'"), Some(1));
+    assert_eq!(file.production(0), "Expr -> Expr '+' Term");
+    assert_eq!(file.item(0, 1), "Expr -> Expr . '+' Term");
+
+    let file = format::parse("%start B; A : %empty | a ; B : A b ; A : B ;").unwrap();
+    assert_eq!(file.variables, vec!["A", "B"]);
+    assert_eq!(file.grammar.entry_points(), &[1]);
+    assert_eq!(file.grammar.productions().get(2), (0, &[Var(1)][..]));
+    assert_eq!(file.production(0), "A -> ε");
+    assert_eq!(file.variable(2), "B'");
+
+    let error = |source| format::parse(source).err().unwrap().to_string();
+    assert_eq!(error("A : a"), "1:5: missing `;` at end of file");
+    assert_eq!(error("A : a ;\nB a ;"), "2:3: expected `:` after `B`");
+    assert_eq!(error("A : 'a ;"), "1:5: unterminated string");
+    assert_eq!(error("%start C; A : a ;"), "1:8: `C` is not defined by a rule");
+    assert_eq!(error("%prec A : a ;"), "1:1: unknown directive `%prec`");
+    assert_eq!(error("# nothing"), "1:1: the grammar has no rules");
+}
+
+#[test]
+fn argument_parsing() {
+    let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let options = parse_args(&args("parse --kind lr1 g.y num '+' --entry Expr num")).unwrap();
+    assert_eq!(options.command, "parse");
+    assert_eq!(options.path, "g.y");
+    assert_eq!(options.kind, AutomatonKind::LR1);
+    assert_eq!(options.entry.as_deref(), Some("Expr"));
+    assert_eq!(options.tokens, vec!["num", "'+'", "num"]);
+
+    assert!(parse_args(&args("check")).is_err());
+    assert!(parse_args(&args("check --kind lr2 g.y")).is_err());
+}
+
+#[test]
+fn check_and_sets() {
+    let (output, ok) = run("check", EXPR);
+    assert!(ok);
+    assert!(output.ends_with("ok\n"));
+
+    let (output, ok) = run("check", "%token unused; A : a | B ; B : B b ; C : c ;");
+    assert!(!ok);
+    assert!(output.contains("unproductive variable: B\n"));
+    assert!(output.contains("unreachable symbol: C\n"));
+    assert!(output.contains("unused terminal: unused\n"));
+
+    let (output, _) = run("sets", "A : B c | %empty ; B : b | %empty ;");
+    assert!(output.contains("A\n  nullable: yes\n  first:    c b\n  follow:   $\n"));
+    assert!(output.contains("B\n  nullable: yes\n  first:    b\n  follow:   c\n"));
+}
+
+#[test]
+fn conflicts_by_kind() {
+    let conflicts = |kind| run_on_source(&options("conflicts", kind), ASSIGN).unwrap();
+    assert!(!conflicts(AutomatonKind::LR0).1);
+    assert!(!conflicts(AutomatonKind::SLR1).1);
+    assert_eq!(conflicts(AutomatonKind::LALR1), ("0 conflicts\n".to_string(), true));
+    assert_eq!(conflicts(AutomatonKind::LR1), ("0 conflicts\n".to_string(), true));
+
+    let (output, ok) = conflicts(AutomatonKind::SLR1);
+    assert!(output.contains("shift/reduce conflict on '='"));
+    assert!(output.contains("  shift:  S -> L . '=' R\n"));
+    assert!(output.contains("  reduce: R -> L .\n"));
+    assert!(output.contains("  reached by: L\n"));
+    assert!(output.contains("  '=' may follow R:\n"));
+    assert!(output.ends_with("1 conflict\n"));
+    assert!(!ok);
+
+    let (output, ok) = run("conflicts", DANGLING_ELSE);
+    assert!(!ok);
+    assert!(output.contains("shift/reduce conflict on else"));
+    assert!(output.contains("  reached by: if cond Stmt\n"));
+}
+
+#[test]
+fn automaton_and_dot() {
+    let (output, _) = run_on_source(&options("automaton", AutomatonKind::LR0), ASSIGN).unwrap();
+    assert!(output.starts_with("state 0\n  S -> . L '=' R\n"));
+    assert!(output.contains("  S' -> . S\n  on '*' goto 1\n"));
+    assert!(output.contains("  on L goto "));
+
+    let (output, _) = run("automaton", ASSIGN);
+    assert!(output.contains("  R -> L .  [$]\n"));
+
+    let (output, _) = run("dot", ASSIGN);
+    assert!(output.starts_with("digraph automaton {"));
+    assert!(output.contains("[label=\"'='\"]"));
+    assert!(output.trim_end().ends_with('}'));
+}
+
+#[test]
+fn table_parse_and_gen() {
+    let (output, ok) = run("table", EXPR);
+    assert!(ok);
+    assert!(output.starts_with("state | $"));
+    assert!(output.contains("acc"));
+
+    let mut stats = options("table", AutomatonKind::LALR1);
+    stats.stats = true;
+    let (output, _) = run_on_source(&stats, EXPR).unwrap();
+    assert!(output.starts_with("states:  "));
+
+    let error = run_on_source(&options("table", AutomatonKind::LALR1), DANGLING_ELSE).unwrap_err();
+    assert!(error.contains("lrtool conflicts"));
+
+    let mut parse = options("parse", AutomatonKind::LALR1);
+    parse.tokens = vec!["num".to_string(), "+".to_string(), "num".to_string()];
+    let (output, ok) = run_on_source(&parse, EXPR).unwrap();
+    assert!(ok);
+    assert_eq!(output, "shift  num\nreduce Term -> num\nreduce Expr -> Term\nshift  '+'\n\
+                        shift  num\nreduce Term -> num\nreduce Expr -> Expr '+' Term\naccepted\n");
+
+    parse.tokens.pop();
+    let (output, ok) = run_on_source(&parse, EXPR).unwrap();
+    assert!(!ok);
+    assert!(output.ends_with("shift  '+'\nerror: unexpected end of input at position 2, in state 6\n"));
+
+    parse.trace = true;
+    let (output, _) = run_on_source(&parse, EXPR).unwrap();
+    assert_eq!(output, "\
+step | stack | lookahead | action
+0    | 0     | num       | shift 1
+1    | 0 1   | '+'       | reduce 3, goto 4
+2    | 0 4   | '+'       | reduce 1, goto 3
+3    | 0 3   | '+'       | shift 6
+4    | 0 3 6 | $         | error
+error: unexpected end of input at position 2, in state 6
+");
+
+    parse.tokens.push("-".to_string());
+    assert_eq!(run_on_source(&parse, EXPR).unwrap_err(), "unknown terminal `-`");
+
+    let (output, ok) = run("gen", EXPR);
+    assert!(ok);
+    assert!(output.contains("pub const TERMINALS: &[&str] = &[\"num\", \"'+'\", \"'*'\", \"'('\", \"')'\"];"));
+    assert!(output.contains("pub const START_STATES: &[u32] = &[0];"));
+    assert!(output.contains("pub const REDUCTIONS: &[(u32, u32)] = &[(0, 3), (0, 1), (1, 3), (1, 1), (1, 3), (2, 1)];"));
+}
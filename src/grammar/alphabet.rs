@@ -0,0 +1,98 @@
+/// The terminals of a grammar, numbered in order, with their names and
+/// kinds. Declaring them makes terminals that no production uses, such as
+/// reserved keywords, part of the grammar and of the tables built from it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Alphabet {
+    terminals: Vec<TerminalInfo>,
+}
+
+/// What is known of a terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalInfo {
+    /// The name, if any.
+    pub name: Option<String>,
+    /// How the terminal is used.
+    pub kind: TerminalKind,
+}
+
+/// How a terminal is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TerminalKind {
+    /// An ordinary token, which productions use.
+    Token,
+    /// A token the lexer produces but the parser never sees, e.g. whitespace
+    /// or comments.
+    Skip,
+    /// A token the lexer produces for invalid input.
+    Error,
+}
+
+impl Alphabet {
+    /// The empty alphabet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The alphabet of `count` unnamed tokens.
+    #[must_use]
+    pub fn unnamed(count: usize) -> Self {
+        Self { terminals: vec![TerminalInfo { name: None, kind: TerminalKind::Token }; count] }
+    }
+
+    /// Adds the token `name`, unless already present, and returns its index.
+    pub fn add(&mut self, name: &str) -> usize {
+        self.add_kind(name, TerminalKind::Token)
+    }
+
+    /// Adds the terminal `name` of the given kind, unless a terminal of that
+    /// name is already present, and returns its index.
+    pub fn add_kind(&mut self, name: &str, kind: TerminalKind) -> usize {
+        self.lookup(name).unwrap_or_else(|| {
+            self.terminals.push(TerminalInfo { name: Some(name.to_string()), kind });
+            self.terminals.len() - 1
+        })
+    }
+
+    /// The number of terminals.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.terminals.len()
+    }
+
+    /// Whether there are no terminals.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.terminals.is_empty()
+    }
+
+    /// Whether `word` is a terminal of the alphabet.
+    #[must_use]
+    pub fn contains(&self, word: usize) -> bool {
+        word < self.terminals.len()
+    }
+
+    /// What is known of terminal `word`.
+    #[must_use]
+    pub fn get(&self, word: usize) -> Option<&TerminalInfo> {
+        self.terminals.get(word)
+    }
+
+    /// The name of terminal `word`, if it has one.
+    #[must_use]
+    pub fn name(&self, word: usize) -> Option<&str> {
+        self.get(word)?.name.as_deref()
+    }
+
+    /// The kind of terminal `word`.
+    #[must_use]
+    pub fn kind(&self, word: usize) -> Option<TerminalKind> {
+        self.get(word).map(|info| info.kind)
+    }
+
+    /// The terminal named `name`.
+    #[must_use]
+    pub fn lookup(&self, name: &str) -> Option<usize> {
+        self.terminals.iter().position(|info| info.name.as_deref() == Some(name))
+    }
+}
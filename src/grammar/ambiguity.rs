@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use super::{Grammar, Symbol};
+use super::generate::{min_lens, production_len};
+
+/// A derivation tree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Tree {
+    /// A terminal.
+    Word(usize),
+    /// A variable, expanded with `production`.
+    Node {
+        /// The production applied.
+        production: usize,
+        /// One subtree per symbol of the production.
+        children: Vec<Tree>,
+    },
+}
+
+/// A sentence with two distinct derivation trees, found by
+/// [`Grammar::ambiguity`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    entry: usize,
+    sentence: Vec<usize>,
+    trees: [Tree; 2],
+}
+
+impl Tree {
+    /// The terminals at the leaves, from left to right.
+    #[must_use]
+    pub fn sentence(&self) -> Vec<usize> {
+        let mut sentence = Vec::new();
+        self.collect_words(&mut sentence);
+        sentence
+    }
+
+    fn collect_words(&self, sentence: &mut Vec<usize>) {
+        match self {
+            Self::Word(a) => sentence.push(*a),
+            Self::Node { children, .. } => for child in children {
+                child.collect_words(sentence);
+            },
+        }
+    }
+}
+
+impl Ambiguity {
+    /// The entry point deriving the sentence.
+    #[must_use]
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// The ambiguous sentence.
+    #[must_use]
+    pub fn sentence(&self) -> &[usize] {
+        &self.sentence
+    }
+
+    /// Two distinct derivation trees of the sentence, rooted at the entry
+    /// point.
+    #[must_use]
+    pub fn trees(&self) -> &[Tree; 2] {
+        &self.trees
+    }
+}
+
+/// The number of sentences the search keeps, over all variables and
+/// lengths, beyond which it gives up.
+const MAX_SENTENCES: usize = 1 << 16;
+
+/// See [`Grammar::ambiguity`].
+pub(super) fn find_ambiguity(grammar: &Grammar, max_len: usize) -> Option<Ambiguity> {
+    let mut search = Search {
+        grammar,
+        min_lens: min_lens(grammar),
+        tables: vec![Vec::new(); grammar.rules().len()],
+        sentence_count: 0,
+    };
+
+    for n in 0..=max_len {
+        if !search.extend_tables() {
+            break;
+        }
+        for (entry, &var) in grammar.entry_points().iter().enumerate() {
+            // Sentences are compared in order, so that the witness does not
+            // depend on hashing.
+            let witness = search.tables[var][n].iter()
+                .filter(|(_, trees)| trees.len() == 2)
+                .min_by(|(left, _), (right, _)| left.cmp(right));
+            if let Some((sentence, trees)) = witness {
+                return Some(Ambiguity {
+                    entry,
+                    sentence: sentence.clone(),
+                    trees: [trees[0].clone(), trees[1].clone()],
+                });
+            }
+        }
+    }
+
+    None
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// Up to two trees for every sentence of every variable, by length.
+struct Search<'a> {
+    grammar: &'a Grammar,
+    min_lens: Vec<usize>,
+    tables: Vec<Vec<HashMap<Vec<usize>, Vec<Tree>>>>, // tables[A][n][w] are the trees deriving w of length n from A
+    sentence_count: usize,                            // over all tables
+}
+
+impl Search<'_> {
+    /// Computes the trees of sentences of the next length. Since a variable
+    /// may derive another one with nothing around it, this is a fixpoint,
+    /// which terminates because each sentence keeps at most two trees.
+    /// Returns `false` if the tables outgrow [`MAX_SENTENCES`], leaving the
+    /// new length incomplete.
+    fn extend_tables(&mut self) -> bool {
+        let n = self.tables[0].len();
+        for table in &mut self.tables {
+            table.push(HashMap::new());
+        }
+
+        let mut done = false;
+        while !done {
+            done = true;
+            for (j, (A, alt)) in self.grammar.productions().into_iter().enumerate() {
+                for (sentence, children) in self.combine(alt, n) {
+                    for children in children {
+                        let tree = Tree::Node { production: j, children };
+                        let sentence_count = &mut self.sentence_count;
+                        let trees = self.tables[A][n].entry(sentence.clone()).or_insert_with(|| {
+                            *sentence_count += 1;
+                            Vec::new()
+                        });
+                        if trees.len() < 2 && !trees.contains(&tree) {
+                            trees.push(tree);
+                            done = false;
+                        }
+                    }
+                }
+                if self.sentence_count > MAX_SENTENCES {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// The sentences of length `n` derived from `symbols`, each with up to
+    /// two distinct sequences of subtrees. Stops early once there are more
+    /// than [`MAX_SENTENCES`] of them.
+    fn combine(&self, symbols: &[Symbol], n: usize) -> HashMap<Vec<usize>, Vec<Vec<Tree>>> {
+        let mut combined: HashMap<Vec<usize>, Vec<Vec<Tree>>> = HashMap::new();
+        let (first, rest) = match symbols.split_first() {
+            Some(split) => split,
+            None => {
+                if n == 0 {
+                    combined.insert(Vec::new(), vec![Vec::new()]);
+                }
+                return combined;
+            },
+        };
+        let rest_len = production_len(rest, &self.min_lens);
+        if rest_len > n {
+            return combined;
+        }
+
+        for k in 0..=n - rest_len {
+            let heads: Vec<(Vec<usize>, Vec<Tree>)> = match *first {
+                Symbol::Terminal(a) if k == 1 => vec![(vec![a], vec![Tree::Word(a)])],
+                Symbol::Terminal(_) => continue,
+                Symbol::Variable(B) if k >= self.min_lens[B] => self.tables[B][k].iter()
+                    .map(|(sentence, trees)| (sentence.clone(), trees.clone()))
+                    .collect(),
+                Symbol::Variable(_) => continue,
+            };
+            if heads.is_empty() {
+                continue;
+            }
+            let tails = self.combine(rest, n - k);
+            for (head, head_trees) in &heads {
+                for (tail, tail_children) in &tails {
+                    let sentence: Vec<usize> = head.iter().chain(tail).copied().collect();
+                    let children = combined.entry(sentence).or_default();
+                    for head_tree in head_trees {
+                        for tail_children in tail_children {
+                            if children.len() < 2 {
+                                children.push(std::iter::once(head_tree.clone()).chain(tail_children.iter().cloned()).collect());
+                            }
+                        }
+                    }
+                    if combined.len() > MAX_SENTENCES {
+                        return combined;
+                    }
+                }
+            }
+        }
+        combined
+    }
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use super::{Grammar, GrammarBuilder, GrammarBuildError, Symbol};
+
+/// An EBNF expression, desugared by [`EbnfBuilder`] into a symbol and, unless
+/// it is a plain symbol, a fresh variable with its own productions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    /// A terminal or variable.
+    Symbol(Symbol),
+    /// `X*`, zero or more repetitions.
+    Star(Box<Expr>),
+    /// `X+`, one or more repetitions.
+    Plus(Box<Expr>),
+    /// `X?`, zero or one occurrence.
+    Optional(Box<Expr>),
+    /// `(a b | c)`, grouped alternatives.
+    Group(Vec<Vec<Expr>>),
+    /// `sep_by(X, s)`, zero or more `X`s separated by `s`.
+    SepBy(Box<Expr>, Box<Expr>),
+    /// `sep_by1(X, s)`, one or more `X`s separated by `s`.
+    SepBy1(Box<Expr>, Box<Expr>),
+    /// The parameter of the enclosing template with the given index.
+    Param(usize),
+    /// A template applied to arguments.
+    Apply(usize, Vec<Expr>),
+}
+
+/// The construct a synthetic variable was desugared from, along with the
+/// shape of its productions. `L` stands for the synthetic variable itself.
+/// Productions are numbered in the order listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Synthetic {
+    /// `L -> | L X`
+    Star,
+    /// `L -> X | L X`
+    Plus,
+    /// `L -> | X`
+    Optional,
+    /// One production per alternative.
+    Group,
+    /// `L -> | P`, where `P` is [`SepBy1`](Synthetic::SepBy1).
+    SepBy,
+    /// `L -> X | L s X`
+    SepBy1,
+    /// An instantiation of the given template, with one production per
+    /// production of the template.
+    Template(usize),
+}
+
+/// Consuming builder accepting EBNF expressions in productions.
+///
+/// Unlike [`GrammarBuilder`], it has no `&mut self` methods or handles:
+/// productions always go to the last rule or template started, so rules
+/// must be added one after another, referring to each other by index.
+///
+/// Rules added with [`new_rule`](EbnfBuilder::new_rule) keep their indices
+/// in the resulting grammar. Synthetic variables are numbered after them,
+/// with each distinct expression desugared only once. Repetitions are
+/// desugared into left recursive productions, which LR parsers handle in
+/// constant stack space.
+///
+/// Templates added with [`new_template`](EbnfBuilder::new_template) are
+/// rules parameterized over symbols, numbered separately from rules. Each
+/// distinct application of a template, after desugaring its arguments, is
+/// instantiated into a single synthetic variable, and only if it is used.
+///
+/// Misuse, such as a production added before any rule or template, is
+/// reported by [`build`](EbnfBuilder::build) rather than by panicking.
+pub struct EbnfBuilder {
+    rules: Vec<Vec<Vec<Expr>>>,
+    templates: Vec<(usize, Vec<Vec<Expr>>)>,
+    in_template: bool,
+    misuse: Option<GrammarBuildError>,
+}
+
+/// A grammar desugared from EBNF, together with the origin of its synthetic
+/// variables.
+#[derive(Debug, Clone)]
+pub struct EbnfGrammar {
+    grammar: Grammar,
+    origins: Vec<Option<Synthetic>>,
+}
+
+impl Expr {
+    /// `x*`
+    pub fn star<X: Into<Expr>>(x: X) -> Self {
+        Self::Star(Box::new(x.into()))
+    }
+
+    /// `x+`
+    pub fn plus<X: Into<Expr>>(x: X) -> Self {
+        Self::Plus(Box::new(x.into()))
+    }
+
+    /// `x?`
+    pub fn optional<X: Into<Expr>>(x: X) -> Self {
+        Self::Optional(Box::new(x.into()))
+    }
+
+    /// `(alt | alt | ...)`
+    pub fn group<A, I, X>(alts: A) -> Self
+    where
+        A: IntoIterator<Item = I>,
+        I: IntoIterator<Item = X>,
+        X: Into<Expr>,
+    {
+        Self::Group(alts.into_iter().map(|alt| alt.into_iter().map(Into::into).collect()).collect())
+    }
+
+    /// `sep_by(x, sep)`
+    pub fn sep_by<X: Into<Expr>, S: Into<Expr>>(x: X, sep: S) -> Self {
+        Self::SepBy(Box::new(x.into()), Box::new(sep.into()))
+    }
+
+    /// `sep_by1(x, sep)`
+    pub fn sep_by1<X: Into<Expr>, S: Into<Expr>>(x: X, sep: S) -> Self {
+        Self::SepBy1(Box::new(x.into()), Box::new(sep.into()))
+    }
+
+    /// The parameter with index `param` of the enclosing template.
+    #[must_use]
+    pub fn param(param: usize) -> Self {
+        Self::Param(param)
+    }
+
+    /// `template(arg, ...)`
+    pub fn apply<I, X>(template: usize, args: I) -> Self
+    where
+        I: IntoIterator<Item = X>,
+        X: Into<Expr>,
+    {
+        Self::Apply(template, args.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Symbol> for Expr {
+    fn from(symbol: Symbol) -> Self {
+        Self::Symbol(symbol)
+    }
+}
+
+impl EbnfBuilder {
+    /// Creates a builder with no rules.
+    #[must_use]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            templates: Vec::new(),
+            in_template: false,
+            misuse: None,
+        }
+    }
+
+    /// Starts a new rule, to which subsequent productions are added.
+    #[must_use]
+    pub fn new_rule(mut self) -> Self {
+        self.rules.push(Vec::new());
+        self.in_template = false;
+        self
+    }
+
+    /// Starts a new template taking `param_count` parameters, to which
+    /// subsequent productions are added.
+    #[must_use]
+    pub fn new_template(mut self, param_count: usize) -> Self {
+        self.templates.push((param_count, Vec::new()));
+        self.in_template = true;
+        self
+    }
+
+    /// Adds a production to the current rule or template.
+    #[must_use]
+    pub fn add_production<I, X>(mut self, exprs: I) -> Self
+    where
+        I: IntoIterator<Item = X>,
+        X: Into<Expr>,
+    {
+        let alt = exprs.into_iter().map(Into::into).collect();
+        let alts = if self.in_template {
+            self.templates.last_mut().map(|(_, alts)| alts)
+        } else {
+            self.rules.last_mut()
+        };
+        match alts {
+            Some(alts) => alts.push(alt),
+            None => {
+                self.misuse.get_or_insert(GrammarBuildError::NoRule);
+            },
+        }
+        self
+    }
+
+    /// # Errors
+    /// Returns the first misuse of the builder, or else an error if some
+    /// variable, template or parameter is out of range, or some template is
+    /// applied to the wrong number of arguments.
+    /// The position reported is that of the top-level expression containing
+    /// the offending one, and templates are numbered after rules.
+    pub fn build(self) -> Result<EbnfGrammar, GrammarBuildError> {
+        if let Some(misuse) = self.misuse {
+            return Err(misuse);
+        }
+
+        let var_count = self.rules.len();
+        let arities: Vec<usize> = self.templates.iter().map(|(param_count, _)| *param_count).collect();
+        let bodies = self.rules.iter().map(|rule| (0, rule)).chain(self.templates.iter().map(|(n, alts)| (*n, alts)));
+        for (i, (param_count, alts)) in bodies.enumerate() {
+            for (j, alt) in alts.iter().enumerate() {
+                for (k, expr) in alt.iter().enumerate() {
+                    check(expr, var_count, &arities, param_count).map_err(|error| match error {
+                        GrammarBuildError::InvalidVariable { variable, .. } => {
+                            GrammarBuildError::InvalidVariable { rule: i, production: j, pos: k, variable }
+                        },
+                        error => error,
+                    })?;
+                }
+            }
+        }
+
+        let mut desugar = Desugar {
+            var_count,
+            templates: &self.templates,
+            synthetic: Vec::new(),
+            cache: HashMap::new(),
+            instances: HashMap::new(),
+            depth: 0,
+        };
+        let rules: Vec<Vec<Vec<Symbol>>> = self.rules.iter()
+            .map(|rule| rule.iter().map(|alt| desugar.sequence(alt)).collect())
+            .collect::<Result<_, _>>()?;
+
+        let mut builder = GrammarBuilder::new();
+        let mut origins = vec![None; var_count];
+        let synthetic = desugar.synthetic.into_iter().map(|(kind, alts)| {
+            origins.push(Some(kind));
+            alts
+        });
+        for rule in rules.into_iter().chain(synthetic) {
+            builder = builder.new_rule();
+            for alt in rule {
+                builder = builder.add_production(alt);
+            }
+        }
+        origins.push(None);
+
+        Ok(EbnfGrammar {
+            grammar: builder.build()?,
+            origins,
+        })
+    }
+}
+
+impl EbnfGrammar {
+    /// The desugared grammar.
+    #[must_use]
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    /// Consumes the result, returning the desugared grammar.
+    #[must_use]
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    /// The construct `var` was desugared from, or `None` if it is one of the
+    /// rules added to the builder.
+    #[must_use]
+    pub fn origin(&self, var: usize) -> Option<Synthetic> {
+        self.origins[var]
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// Nesting depth of template instantiations beyond which expansion is
+/// considered unbounded.
+const MAX_DEPTH: usize = 64;
+
+struct Desugar<'a> {
+    var_count: usize,
+    templates: &'a [(usize, Vec<Vec<Expr>>)],
+    synthetic: Vec<(Synthetic, Vec<Vec<Symbol>>)>,
+    cache: HashMap<Expr, usize>,
+    instances: HashMap<(usize, Vec<Symbol>), usize>,
+    depth: usize,
+}
+
+impl Desugar<'_> {
+    fn sequence(&mut self, exprs: &[Expr]) -> Result<Vec<Symbol>, GrammarBuildError> {
+        exprs.iter().map(|expr| self.symbol(expr)).collect()
+    }
+
+    fn new_var(&mut self, kind: Synthetic) -> usize {
+        self.synthetic.push((kind, Vec::new()));
+        self.var_count + self.synthetic.len() - 1
+    }
+
+    fn symbol(&mut self, expr: &Expr) -> Result<Symbol, GrammarBuildError> {
+        let kind = match expr {
+            Expr::Symbol(symbol) => return Ok(*symbol),
+            Expr::Param(_) => unreachable!(), // substituted on instantiation
+            Expr::Apply(template, args) => return self.instantiate(*template, args),
+            Expr::Star(_) => Synthetic::Star,
+            Expr::Plus(_) => Synthetic::Plus,
+            Expr::Optional(_) => Synthetic::Optional,
+            Expr::Group(_) => Synthetic::Group,
+            Expr::SepBy(..) => Synthetic::SepBy,
+            Expr::SepBy1(..) => Synthetic::SepBy1,
+        };
+        if let Some(&var) = self.cache.get(expr) {
+            return Ok(Symbol::Variable(var));
+        }
+
+        // Number the variable before the ones its operands desugar into.
+        let var = self.new_var(kind);
+        self.cache.insert(expr.clone(), var);
+        let L = Symbol::Variable(var);
+
+        let alts = match expr {
+            Expr::Star(x) => vec![vec![], vec![L, self.symbol(x)?]],
+            Expr::Plus(x) => {
+                let X = self.symbol(x)?;
+                vec![vec![X], vec![L, X]]
+            },
+            Expr::Optional(x) => vec![vec![], vec![self.symbol(x)?]],
+            Expr::Group(alts) => alts.iter().map(|alt| self.sequence(alt)).collect::<Result<_, _>>()?,
+            Expr::SepBy(x, sep) => vec![vec![], vec![self.symbol(&Expr::SepBy1(x.clone(), sep.clone()))?]],
+            Expr::SepBy1(x, sep) => {
+                let X = self.symbol(x)?;
+                vec![vec![X], vec![L, self.symbol(sep)?, X]]
+            },
+            Expr::Symbol(_) | Expr::Param(_) | Expr::Apply(..) => unreachable!(),
+        };
+        self.synthetic[var - self.var_count].1 = alts;
+        Ok(L)
+    }
+
+    fn instantiate(&mut self, template: usize, args: &[Expr]) -> Result<Symbol, GrammarBuildError> {
+        let args = self.sequence(args)?;
+        if let Some(&var) = self.instances.get(&(template, args.clone())) {
+            return Ok(Symbol::Variable(var));
+        }
+        if self.depth == MAX_DEPTH {
+            return Err(GrammarBuildError::UnboundedExpansion { template });
+        }
+
+        let var = self.new_var(Synthetic::Template(template));
+        self.instances.insert((template, args.clone()), var);
+
+        self.depth += 1;
+        let alts = self.templates[template].1.iter()
+            .map(|alt| {
+                let alt: Vec<Expr> = alt.iter().map(|expr| substitute(expr, &args)).collect();
+                self.sequence(&alt)
+            })
+            .collect::<Result<_, _>>();
+        self.depth -= 1;
+
+        self.synthetic[var - self.var_count].1 = alts?;
+        Ok(Symbol::Variable(var))
+    }
+}
+
+/// Replaces the parameters in `expr` with `args`.
+fn substitute(expr: &Expr, args: &[Symbol]) -> Expr {
+    let boxed = |x: &Expr| Box::new(substitute(x, args));
+    match expr {
+        Expr::Symbol(_) => expr.clone(),
+        Expr::Param(i) => Expr::Symbol(args[*i]),
+        Expr::Star(x) => Expr::Star(boxed(x)),
+        Expr::Plus(x) => Expr::Plus(boxed(x)),
+        Expr::Optional(x) => Expr::Optional(boxed(x)),
+        Expr::Group(alts) => Expr::Group(alts.iter().map(|alt| alt.iter().map(|x| substitute(x, args)).collect()).collect()),
+        Expr::SepBy(x, sep) => Expr::SepBy(boxed(x), boxed(sep)),
+        Expr::SepBy1(x, sep) => Expr::SepBy1(boxed(x), boxed(sep)),
+        Expr::Apply(template, exprs) => Expr::Apply(*template, exprs.iter().map(|x| substitute(x, args)).collect()),
+    }
+}
+
+/// Checks that variables, templates and parameters in `expr` are in range,
+/// and that templates are applied to the right number of arguments. Any
+/// [`InvalidVariable`](GrammarBuildError::InvalidVariable) error is returned
+/// without a location.
+fn check(expr: &Expr, var_count: usize, arities: &[usize], param_count: usize) -> Result<(), GrammarBuildError> {
+    let check_all = |exprs: &[Expr]| exprs.iter().try_for_each(|x| check(x, var_count, arities, param_count));
+    match expr {
+        Expr::Symbol(Symbol::Variable(A)) if *A >= var_count => {
+            Err(GrammarBuildError::InvalidVariable { rule: 0, production: 0, pos: 0, variable: *A })
+        },
+        Expr::Symbol(_) => Ok(()),
+        Expr::Param(param) if *param >= param_count => Err(GrammarBuildError::InvalidParameter { param: *param }),
+        Expr::Param(_) => Ok(()),
+        Expr::Star(x) | Expr::Plus(x) | Expr::Optional(x) => check(x, var_count, arities, param_count),
+        Expr::Group(alts) => alts.iter().try_for_each(|alt| check_all(alt)),
+        Expr::SepBy(x, sep) | Expr::SepBy1(x, sep) => {
+            check(x, var_count, arities, param_count)?;
+            check(sep, var_count, arities, param_count)
+        },
+        Expr::Apply(template, args) => match arities.get(*template) {
+            None => Err(GrammarBuildError::InvalidTemplate { template: *template }),
+            Some(&expected) if expected != args.len() => {
+                Err(GrammarBuildError::InvalidArity { template: *template, expected, found: args.len() })
+            },
+            Some(_) => check_all(args),
+        },
+    }
+}
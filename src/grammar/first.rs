@@ -1,6 +1,5 @@
-use std::collections::BTreeSet;
-use std::iter::once;
-use super::{Grammar, Symbol, Nullable};
+use std::collections::VecDeque;
+use super::{Grammar, Symbol, Nullable, Step, TokenSet};
 use crate::transitive_closure;
 
 /// For non-terminal A, first[A] is the set of terminals that can appear at
@@ -8,32 +7,92 @@ use crate::transitive_closure;
 /// for this behaviour, see [nullability](Grammar::nullability).
 #[derive(Debug)]
 pub struct First {
-    firsts: Vec<usize>,
-    var_ranges: Vec<usize>,
+    firsts: Vec<TokenSet>,
+    word_count: usize,
 }
 
 impl First {
     #[must_use]
     pub(super) fn new(grammar: &Grammar, nullable: &Nullable) -> Self {
-        let var_firsts = compute_var_firsts(grammar, nullable);
-        let var_ranges = once(0)
-            .chain(
-                var_firsts.iter()
-                .map(BTreeSet::len)
-                .scan(0, |cumsum, len| { *cumsum += len; Some(*cumsum) }))
-            .collect();
-
         Self {
-            firsts: var_firsts.into_iter().flatten().collect(),
-            var_ranges,
+            firsts: compute_var_firsts(grammar, nullable),
+            word_count: grammar.word_count(),
         }
     }
 
-    pub fn get(&self, var: usize) -> &[usize] {
-        &self.firsts[self.var_ranges[var]..self.var_ranges[var+1]]
+    pub fn get(&self, var: usize) -> &TokenSet {
+        &self.firsts[var]
+    }
+
+    /// The terminals that can begin a sentence derived from `symbols`, and
+    /// whether `symbols` derives the empty sentence.
+    #[must_use]
+    pub fn first_of(&self, symbols: &[Symbol], nullable: &Nullable) -> (TokenSet, bool) {
+        let mut first = TokenSet::new(self.word_count);
+        for &symbol in symbols {
+            match symbol {
+                Symbol::Terminal(a) => {
+                    first.insert(Some(a));
+                    return (first, false);
+                },
+                Symbol::Variable(B) => {
+                    first.union_with(&self.firsts[B]);
+                    if !nullable.get(B) {
+                        return (first, false);
+                    }
+                },
+            }
+        }
+        (first, true)
     }
 }
 
+/// See [`Grammar::explain_first`].
+pub(super) fn explain_first(grammar: &Grammar, nullable: &Nullable, var: usize, word: usize) -> Option<Vec<Step>> {
+    // Breadth first search over the variables that can begin `var`, so that
+    // the chain found is a shortest one.
+    let mut parent: Vec<Option<(usize, Step)>> = vec![None; grammar.rules().len()];
+    let mut visited = vec![false; grammar.rules().len()];
+    let mut queue = VecDeque::from([var]);
+    visited[var] = true;
+
+    while let Some(A) = queue.pop_front() {
+        let rule = grammar.rules().get(A);
+        for (j, alt) in rule.production_ids().zip(rule.alts()) {
+            for (pos, &symbol) in alt.iter().enumerate() {
+                let step = Step { production: j, pos };
+                match symbol {
+                    Symbol::Terminal(a) => {
+                        if a == word {
+                            let mut chain = vec![step];
+                            let mut B = A;
+                            while let Some((A, step)) = parent[B] {
+                                chain.push(step);
+                                B = A;
+                            }
+                            chain.reverse();
+                            return Some(chain);
+                        }
+                        break;
+                    },
+                    Symbol::Variable(B) => {
+                        if !visited[B] {
+                            visited[B] = true;
+                            parent[B] = Some((A, step));
+                            queue.push_back(B);
+                        }
+                        if !nullable.get(B) {
+                            break;
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    None
+}
+
 // =================
 // === INTERNALS ===
 // =================
@@ -50,9 +109,9 @@ impl First {
 /// ```
 /// 
 /// Hence, we can compute `first` by applying the transitive closure algorithm.
-fn compute_var_firsts(grammar: &Grammar, nullable: &Nullable) -> Vec<BTreeSet<usize>> {
+fn compute_var_firsts(grammar: &Grammar, nullable: &Nullable) -> Vec<TokenSet> {
     let var_count = grammar.rules().len();
-    let mut first = vec![BTreeSet::new(); var_count];
+    let mut first = vec![TokenSet::new(grammar.word_count()); var_count];
     let mut dependency_matrix = vec![false; var_count * var_count];
     
     // Initialise first to trivial values and fill dependency_matrix          
@@ -60,7 +119,7 @@ fn compute_var_firsts(grammar: &Grammar, nullable: &Nullable) -> Vec<BTreeSet<us
         for &symbol in beta {
             match symbol {
                 Symbol::Terminal(a) => {
-                    first[A].insert(a);
+                    first[A].insert(Some(a));
                     break;
                 }
                 Symbol::Variable(B) => {
@@ -78,11 +137,11 @@ fn compute_var_firsts(grammar: &Grammar, nullable: &Nullable) -> Vec<BTreeSet<us
         (0..var_count).filter(move |B| dependency_matrix_ref[A * var_count + B])
     };
 
-    let extend = |A: &mut BTreeSet<usize>, B: &BTreeSet<usize>| {
-        A.extend(B);
+    let extend = |A: &mut TokenSet, B: &TokenSet| {
+        A.union_with(B);
     };
 
     transitive_closure(&mut first, left_dependencies, extend);
 
     first
-}
\ No newline at end of file
+}
@@ -1,76 +1,194 @@
-use std::collections::BTreeSet;
-use std::iter::once;
-use super::{Grammar, Symbol, Nullable, First};
+use std::collections::VecDeque;
+use super::{Grammar, Symbol, Nullable, First, Step, TokenSet};
+use super::first::explain_first;
+use crate::transitive_closure;
 
 /// A utility struct that, for each unique variable present in a 
 /// grammar, stores the set of terminals (the follow set) that can
 /// appear immediately after in a sentence.
 /// 
-/// Sets of tokens are represented as slices of type `Option<usize>`,
-/// where `None` represents EOF (End Of File).  
+/// Sets of tokens are represented as [`TokenSet`]s, which yield tokens of
+/// type `Option<usize>`, where `None` represents EOF (End Of File).  
 #[derive(Debug)]
 pub struct Follow {
-    follows: Vec<Option<usize>>,
-    var_ranges: Vec<usize>,
+    follows: Vec<TokenSet>,
 }
 
 impl Follow {
     #[must_use]
     pub(super) fn new(grammar: &Grammar, nullable: &Nullable, first: &First) -> Self {
-        let var_follows = compute_var_follows(grammar, nullable, first);
-        let var_ranges = once(0)
-            .chain(
-                var_follows.iter()
-                .map(BTreeSet::len)
-                .scan(0, |cumsum, len| { *cumsum += len; Some(*cumsum) }))
-            .collect();
-
         Self {
-            follows: var_follows.into_iter().flatten().collect(),
-            var_ranges,
+            follows: compute_var_follows(grammar, nullable, first),
         }
     }
 
-    pub fn get(&self, var: usize) -> &[Option<usize>] {
-        &self.follows[self.var_ranges[var]..self.var_ranges[var+1]]
+    pub fn get(&self, var: usize) -> &TokenSet {
+        &self.follows[var]
     }
 }
 
+/// Why a token follows a variable, as found by [`Grammar::explain_follow`].
+///
+/// The variable occurs in the first production of `occurrences`, whose
+/// left-hand side occurs in the next one, and so on, each followed by a
+/// nullable suffix except for the last. In the last production, the token is
+/// begun by the symbol reached through `first`, a chain as returned by
+/// [`Grammar::explain_first`] except that it starts in that same production.
+/// For EOF, the last production is an augmented start production, and
+/// `first` is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FollowExplanation {
+    occurrences: Vec<Step>,
+    first: Vec<Step>,
+}
+
+impl FollowExplanation {
+    /// The occurrences leading from the variable to the production where
+    /// the token is introduced.
+    #[must_use]
+    pub fn occurrences(&self) -> &[Step] {
+        &self.occurrences
+    }
+
+    /// The derivation from the symbol following the last occurrence down to
+    /// the token.
+    #[must_use]
+    pub fn first(&self) -> &[Step] {
+        &self.first
+    }
+}
+
+/// See [`Grammar::explain_follow`].
+pub(super) fn explain_follow(
+    grammar: &Grammar,
+    nullable: &Nullable,
+    first: &First,
+    var: usize,
+    word: Option<usize>,
+) -> Option<FollowExplanation> {
+    let var_count = grammar.rules().len();
+    let start_var = grammar.start_var(0);
+
+    // Breadth first search upwards, from `var` to the left-hand sides of the
+    // productions it ends modulo a nullable suffix.
+    let mut parent: Vec<Option<(usize, Step)>> = vec![None; var_count];
+    let mut visited = vec![false; var_count];
+    let mut queue = VecDeque::from([var]);
+    visited[var] = true;
+
+    let occurrences_to = |parent: &[Option<(usize, Step)>], mut B: usize, mut occurrences: Vec<Step>| {
+        while let Some((A, step)) = parent[B] {
+            occurrences.push(step);
+            B = A;
+        }
+        occurrences
+    };
+
+    while let Some(A) = queue.pop_front() {
+        if A >= start_var && word.is_none() {
+            let mut occurrences = occurrences_to(&parent, A, Vec::new());
+            occurrences.reverse();
+            return Some(FollowExplanation { occurrences, first: Vec::new() });
+        }
+
+        for (j, (B, alt)) in grammar.productions().into_iter().enumerate() {
+            for pos in (0..alt.len()).filter(|&pos| alt[pos] == Symbol::Variable(A)) {
+                let occurrence = Step { production: j, pos };
+                let mut suffix_nullable = true;
+                for (next, &symbol) in alt.iter().enumerate().skip(pos + 1) {
+                    let (begins, first_chain) = match symbol {
+                        Symbol::Terminal(a) => (word == Some(a), Some(Vec::new())),
+                        Symbol::Variable(C) => match word {
+                            Some(a) if first.get(C).contains(word) => (true, explain_first(grammar, nullable, C, a)),
+                            _ => (false, None),
+                        },
+                    };
+                    if begins {
+                        let mut occurrences = occurrences_to(&parent, A, vec![occurrence]);
+                        occurrences.reverse();
+                        let mut chain = vec![Step { production: j, pos: next }];
+                        chain.extend(first_chain.unwrap());
+                        return Some(FollowExplanation { occurrences, first: chain });
+                    }
+                    if !matches!(symbol, Symbol::Variable(C) if nullable.get(C)) {
+                        suffix_nullable = false;
+                        break;
+                    }
+                }
+                if suffix_nullable && !visited[B] {
+                    visited[B] = true;
+                    parent[B] = Some((A, occurrence));
+                    queue.push_back(B);
+                }
+            }
+        }
+    }
+
+    None
+}
+
 // =================
 // === INTERNALS ===
 // =================
 
-/// Constructs the follow sets for each unique variable in grammar.
-fn compute_var_follows(grammar: &Grammar, nullable: &Nullable, first: &First) -> Vec<BTreeSet<Option<usize>>> {
-    let mut follow = vec![BTreeSet::<Option<usize>>::new(); grammar.rules().len()];
-    follow.last_mut().unwrap().insert(None);
-
-    let mut done = false;
-    while !done {
-        done = true;
-        for (A, beta) in grammar.productions() {
-            let mut trailer = follow[A].clone();
-            for &symbol in beta.iter().rev() {
-                match symbol {
-                    Symbol::Terminal(b) => {
-                        trailer = once(Some(b)).collect();
+/// Let `A ~ B` hold iff there is a production of the form `B -> pAq`, where
+/// `q` is a nullable sequence of symbols. Let `~+` be the transitive closure
+/// of relation `~`, and `follow'[A]` the union of `first(q)` over all
+/// occurrences `B -> pAq`, plus EOF if `A` is an augmented start variable.
+///
+/// ```text
+/// follow[A] = follow'[A] U union({ follow[B] | A ~ B })
+///           = union({ follow'[B] | A ~* B })
+/// ```
+///
+/// Hence, as with first sets, we can compute `follow` by applying the
+/// transitive closure algorithm.
+fn compute_var_follows(grammar: &Grammar, nullable: &Nullable, first: &First) -> Vec<TokenSet> {
+    let var_count = grammar.rules().len();
+    let word_count = grammar.word_count();
+    let mut follow = vec![TokenSet::new(word_count); var_count];
+    // Unlike for first sets, a dense matrix is too costly here: every
+    // variable at the end of a production depends on its left-hand side.
+    let mut dependencies = vec![Vec::new(); var_count];
+    for follow in &mut follow[grammar.start_var(0)..] {
+        follow.insert(None);
+    }
+
+    // Walk each production right to left, keeping track of the first set of
+    // the tail, and whether it is nullable.
+    for (B, beta) in grammar.productions() {
+        let mut trailer = TokenSet::new(word_count);
+        let mut nullable_tail = true;
+        for &symbol in beta.iter().rev() {
+            match symbol {
+                Symbol::Terminal(b) => {
+                    trailer = TokenSet::new(word_count);
+                    trailer.insert(Some(b));
+                    nullable_tail = false;
+                }
+                Symbol::Variable(A) => {
+                    follow[A].union_with(&trailer);
+                    if nullable_tail && dependencies[A].last() != Some(&B) {
+                        dependencies[A].push(B);
                     }
-                    Symbol::Variable(B) => {
-                        if !trailer.is_subset(&follow[B]) {
-                            follow[B].extend(&trailer);
-                            done = false;
-                        }
-                        let first_B = &first.get(B);
-                        if nullable.get(B) {
-                            trailer.extend(first_B.iter().copied().map(Some));
-                        } else {
-                            trailer = first_B.iter().copied().map(Some).collect();
-                        }
+                    if nullable.get(A) {
+                        trailer.union_with(first.get(A));
+                    } else {
+                        trailer.clone_from(first.get(A));
+                        nullable_tail = false;
                     }
                 }
             }
         }
     }
 
+    let dependencies = |A: usize| dependencies[A].iter().copied();
+
+    let extend = |A: &mut TokenSet, B: &TokenSet| {
+        A.union_with(B);
+    };
+
+    transitive_closure(&mut follow, dependencies, extend);
+
     follow
-}
\ No newline at end of file
+}
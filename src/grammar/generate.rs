@@ -0,0 +1,540 @@
+//! Sentence generation, for fuzzing parsers and for documentation examples.
+//!
+//! Sentences are sequences of terminal indices. All functions take an entry
+//! point index, as for [`Grammar::start_var`], and generate sentences of the
+//! language of that entry point only.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use super::{Grammar, Step, Symbol};
+
+/// Iterator over the sentences of a language in order of length, and
+/// lexicographically within a length. See [`sentences`].
+pub struct Sentences<'a> {
+    grammar:  &'a Grammar,
+    var:      usize,
+    max_len:  usize,
+    min_lens: Vec<usize>,
+    tables:   Vec<Vec<BTreeSet<Vec<usize>>>>, // tables[A][n] is the set of sentences of length n derived from A
+    current:  std::vec::IntoIter<Vec<usize>>,
+}
+
+/// A sentence along with the productions applied in its leftmost
+/// derivation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derivation {
+    sentence:    Vec<usize>,
+    productions: Vec<usize>,
+}
+
+/// Random derivations, drawn from a seeded generator so that runs can be
+/// reproduced.
+///
+/// At each step, a production is chosen uniformly among those that still
+/// allow the derivation to finish within the depth and length limits. When
+/// the two limits pull in different directions, a derivation may get stuck;
+/// it is then retried a few times before giving up.
+pub struct Sampler<'a> {
+    grammar:     &'a Grammar,
+    rng:         XorShift,
+    max_depth:   usize,
+    max_len:     usize,
+    min_lens:    Vec<usize>,
+    min_heights: Vec<usize>,
+}
+
+/// Enumerates the sentences of at most `max_len` terminals derived from
+/// entry point `entry`, shortest first.
+///
+/// Each length is computed only once the previous ones have been yielded, so
+/// taking a prefix of the iterator is cheap even for large `max_len`.
+#[must_use]
+pub fn sentences(grammar: &Grammar, entry: usize, max_len: usize) -> Sentences<'_> {
+    Sentences {
+        grammar,
+        var: grammar.entry_points()[entry],
+        max_len,
+        min_lens: min_lens(grammar),
+        tables: vec![Vec::new(); grammar.rules().len()],
+        current: Vec::new().into_iter(),
+    }
+}
+
+/// A small set of derivations from entry point `entry` that, together, apply
+/// every production that occurs in some derivation at least once.
+///
+/// In the spirit of Purdom's algorithm, each derivation is built for a
+/// production not yet covered, by reaching its left-hand side along a
+/// shortest path from the entry point and completing every other variable
+/// with a shortest sentence.
+#[must_use]
+pub fn coverage(grammar: &Grammar, entry: usize) -> Vec<Derivation> {
+    let min_lens = min_lens(grammar);
+    let shortest = shortest_productions(grammar, &min_lens);
+    let paths = paths_from(grammar, grammar.entry_points()[entry], &min_lens);
+
+    let mut covered = vec![false; grammar.productions().len()];
+    let mut derivations = Vec::new();
+    for (j, (A, alt)) in grammar.productions().into_iter().enumerate() {
+        let productive = alt.iter().all(|&symbol| match symbol {
+            Symbol::Terminal(_) => true,
+            Symbol::Variable(B) => min_lens[B] != usize::MAX,
+        });
+        if covered[j] || !productive || A >= grammar.start_var(0) {
+            continue;
+        }
+        if let Some(path) = &paths[A] {
+            let mut derivation = Derivation { sentence: Vec::new(), productions: Vec::new() };
+            derive_along(grammar, &shortest, grammar.entry_points()[entry], path, Some(j), &mut derivation);
+            for &j in &derivation.productions {
+                covered[j] = true;
+            }
+            derivations.push(derivation);
+        }
+    }
+
+    derivations
+}
+
+/// Sentences one edit away from `sentence` that the language of entry point
+/// `entry` does not contain. Edits are deletions, insertions and
+/// substitutions of a single terminal, and swaps of adjacent terminals.
+///
+/// Each candidate is checked with [`recognize`], so the result is exact
+/// whether or not `sentence` itself is valid.
+#[must_use]
+pub fn near_misses(grammar: &Grammar, entry: usize, sentence: &[usize]) -> Vec<Vec<usize>> {
+    let word_count = grammar.word_count();
+    let mut candidates = Vec::new();
+
+    for i in 0..sentence.len() {
+        let mut deleted = sentence.to_vec();
+        deleted.remove(i);
+        candidates.push(deleted);
+    }
+    for i in 0..=sentence.len() {
+        for a in 0..word_count {
+            let mut inserted = sentence.to_vec();
+            inserted.insert(i, a);
+            candidates.push(inserted);
+        }
+    }
+    for i in 0..sentence.len() {
+        for a in (0..word_count).filter(|&a| a != sentence[i]) {
+            let mut substituted = sentence.to_vec();
+            substituted[i] = a;
+            candidates.push(substituted);
+        }
+    }
+    for i in 1..sentence.len() {
+        if sentence[i - 1] != sentence[i] {
+            let mut swapped = sentence.to_vec();
+            swapped.swap(i - 1, i);
+            candidates.push(swapped);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    candidates.into_iter()
+        .filter(|candidate| seen.insert(candidate.clone()))
+        .filter(|candidate| !recognize(grammar, entry, candidate))
+        .collect()
+}
+
+/// Returns `true` if the language of entry point `entry` contains
+/// `sentence`. This is a general context free recognizer, so it works for
+/// any grammar, ambiguous or not.
+#[must_use]
+pub fn recognize(grammar: &Grammar, entry: usize, sentence: &[usize]) -> bool {
+    let nullable = grammar.nullability();
+    let start_production = grammar.start_production(entry);
+
+    // Earley items (production, dot, origin), one set per position.
+    let mut sets: Vec<Vec<(usize, usize, usize)>> = vec![Vec::new(); sentence.len() + 1];
+    let mut seen: Vec<HashSet<(usize, usize, usize)>> = vec![HashSet::new(); sentence.len() + 1];
+    sets[0].push((start_production, 0, 0));
+    seen[0].insert((start_production, 0, 0));
+
+    for i in 0..=sentence.len() {
+        let mut k = 0;
+        while k < sets[i].len() {
+            let (j, dot, origin) = sets[i][k];
+            k += 1;
+            let (A, alt) = grammar.productions().get(j);
+            let mut add = |sets: &mut Vec<Vec<_>>, i: usize, item| {
+                if seen[i].insert(item) {
+                    sets[i].push(item);
+                }
+            };
+
+            match alt.get(dot) {
+                // Predict, skipping over nullable variables right away.
+                Some(&Symbol::Variable(B)) => {
+                    for p in grammar.rules().get(B).production_ids() {
+                        add(&mut sets, i, (p, 0, i));
+                    }
+                    if nullable.get(B) {
+                        add(&mut sets, i, (j, dot + 1, origin));
+                    }
+                },
+                // Scan.
+                Some(&Symbol::Terminal(a)) => {
+                    if sentence.get(i) == Some(&a) {
+                        add(&mut sets, i + 1, (j, dot + 1, origin));
+                    }
+                },
+                // Complete.
+                None => {
+                    let mut l = 0;
+                    while l < sets[origin].len() {
+                        let (p, pdot, porigin) = sets[origin][l];
+                        l += 1;
+                        if grammar.productions().get(p).1.get(pdot) == Some(&Symbol::Variable(A)) {
+                            add(&mut sets, i, (p, pdot + 1, porigin));
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    seen[sentence.len()].contains(&(start_production, 1, 0))
+}
+
+impl Derivation {
+    /// The derived sentence.
+    #[must_use]
+    pub fn sentence(&self) -> &[usize] {
+        &self.sentence
+    }
+
+    /// The productions applied, in the order of a leftmost derivation.
+    #[must_use]
+    pub fn productions(&self) -> &[usize] {
+        &self.productions
+    }
+}
+
+impl<'a> Sampler<'a> {
+    /// Creates a sampler with the given seed, limited to derivation trees of
+    /// depth 32 by default, and sentences of any length.
+    #[must_use]
+    pub fn new(grammar: &'a Grammar, seed: u64) -> Self {
+        let min_lens = min_lens(grammar);
+        Self {
+            grammar,
+            rng: XorShift::new(seed),
+            max_depth: 32,
+            max_len: usize::MAX,
+            min_heights: min_heights(grammar),
+            min_lens,
+        }
+    }
+
+    /// Limits the depth of derivation trees, counting the root variable.
+    #[must_use]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits the length of sentences.
+    #[must_use]
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Draws a derivation from entry point `entry`, or `None` if none fits
+    /// the limits (or none was found within a few attempts).
+    pub fn sample(&mut self, entry: usize) -> Option<Derivation> {
+        const ATTEMPTS: usize = 16;
+
+        let var = self.grammar.entry_points()[entry];
+        if self.min_heights[var] > self.max_depth || self.min_lens[var] > self.max_len {
+            return None;
+        }
+
+        for _ in 0..ATTEMPTS {
+            let mut derivation = Derivation { sentence: Vec::new(), productions: Vec::new() };
+            let mut pending = 0;
+            if self.expand(var, self.max_depth, &mut pending, &mut derivation) {
+                return Some(derivation);
+            }
+        }
+        None
+    }
+
+    /// Expands `var` into `derivation`, where `pending` is the minimum length
+    /// of the symbols still to be expanded after it.
+    fn expand(&mut self, var: usize, depth: usize, pending: &mut usize, derivation: &mut Derivation) -> bool {
+        let grammar = self.grammar;
+        let budget = self.max_len.saturating_sub(derivation.sentence.len() + *pending);
+        let candidates: Vec<usize> = grammar.rules().get(var).production_ids()
+            .filter(|&j| {
+                let alt = grammar.productions().get(j).1;
+                production_height(alt, &self.min_heights) <= depth
+                    && production_len(alt, &self.min_lens) <= budget
+            })
+            .collect();
+        if candidates.is_empty() {
+            return false;
+        }
+
+        let j = candidates[self.rng.below(candidates.len())];
+        derivation.productions.push(j);
+        let alt = grammar.productions().get(j).1;
+        *pending += production_len(alt, &self.min_lens);
+        for &symbol in alt {
+            match symbol {
+                Symbol::Terminal(a) => {
+                    *pending -= 1;
+                    derivation.sentence.push(a);
+                },
+                Symbol::Variable(B) => {
+                    *pending -= self.min_lens[B];
+                    if !self.expand(B, depth - 1, pending, derivation) {
+                        return false;
+                    }
+                },
+            }
+        }
+        true
+    }
+}
+
+impl Iterator for Sentences<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(sentence) = self.current.next() {
+                return Some(sentence);
+            }
+            let n = self.tables[0].len();
+            if n > self.max_len {
+                return None;
+            }
+            self.extend_tables();
+            self.current = self.tables[self.var][n].iter().cloned().collect::<Vec<_>>().into_iter();
+        }
+    }
+}
+
+impl Sentences<'_> {
+    /// Computes the sentences of the next length for every variable. Since
+    /// a variable may derive another one with nothing around it, this is a
+    /// fixpoint.
+    fn extend_tables(&mut self) {
+        let n = self.tables[0].len();
+        for table in &mut self.tables {
+            table.push(BTreeSet::new());
+        }
+
+        let mut done = false;
+        while !done {
+            done = true;
+            for (A, alt) in self.grammar.productions() {
+                for sentence in self.combine(alt, n) {
+                    if self.tables[A][n].insert(sentence) {
+                        done = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// All sentences of length `n` derived from `symbols`.
+    fn combine(&self, symbols: &[Symbol], n: usize) -> Vec<Vec<usize>> {
+        let (first, rest) = match symbols.split_first() {
+            Some(split) => split,
+            None => return if n == 0 { vec![Vec::new()] } else { Vec::new() },
+        };
+        let rest_len = production_len(rest, &self.min_lens);
+        if rest_len > n {
+            return Vec::new();
+        }
+
+        let mut sentences = Vec::new();
+        for k in 0..=n - rest_len {
+            let heads = match *first {
+                Symbol::Terminal(a) if k == 1 => vec![vec![a]],
+                Symbol::Terminal(_) => continue,
+                Symbol::Variable(B) if k >= self.min_lens[B] => self.tables[B][k].iter().cloned().collect(),
+                Symbol::Variable(_) => continue,
+            };
+            if heads.is_empty() {
+                continue;
+            }
+            let tails = self.combine(rest, n - k);
+            for head in &heads {
+                for tail in &tails {
+                    sentences.push(head.iter().chain(tail).copied().collect());
+                }
+            }
+        }
+        sentences
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// The xorshift64* generator. Not suitable for anything but testing.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        // The state must be nonzero.
+        Self((seed ^ 0x9E37_79B9_7F4A_7C15).max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A number in `0..n`, up to a negligible bias.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// For each variable, the length of its shortest sentence, or `usize::MAX`
+/// if it is unproductive.
+pub(super) fn min_lens(grammar: &Grammar) -> Vec<usize> {
+    let mut min_lens = vec![usize::MAX; grammar.rules().len()];
+    let mut done = false;
+    while !done {
+        done = true;
+        for (A, alt) in grammar.productions() {
+            let len = production_len(alt, &min_lens);
+            if len < min_lens[A] {
+                min_lens[A] = len;
+                done = false;
+            }
+        }
+    }
+    min_lens
+}
+
+/// For each variable, the height of its lowest derivation tree, or
+/// `usize::MAX` if it is unproductive.
+fn min_heights(grammar: &Grammar) -> Vec<usize> {
+    let mut min_heights = vec![usize::MAX; grammar.rules().len()];
+    let mut done = false;
+    while !done {
+        done = true;
+        for (A, alt) in grammar.productions() {
+            let height = production_height(alt, &min_heights);
+            if height < min_heights[A] {
+                min_heights[A] = height;
+                done = false;
+            }
+        }
+    }
+    min_heights
+}
+
+pub(super) fn production_len(symbols: &[Symbol], min_lens: &[usize]) -> usize {
+    symbols.iter().fold(0, |len: usize, &symbol| match symbol {
+        Symbol::Terminal(_) => len.saturating_add(1),
+        Symbol::Variable(B) => len.saturating_add(min_lens[B]),
+    })
+}
+
+fn production_height(symbols: &[Symbol], min_heights: &[usize]) -> usize {
+    symbols.iter()
+        .filter_map(|&symbol| match symbol {
+            Symbol::Terminal(_) => None,
+            Symbol::Variable(B) => Some(min_heights[B]),
+        })
+        .max()
+        .map_or(1, |height| height.saturating_add(1))
+}
+
+/// For each productive variable, a production deriving its shortest
+/// sentence, chosen so that following them always terminates.
+fn shortest_productions(grammar: &Grammar, min_lens: &[usize]) -> Vec<Option<usize>> {
+    // A production achieving the minimum length may still loop through
+    // nullable variables (`A -> A | ...`), so shortest productions are fixed
+    // in the order in which their variables become finished.
+    let mut shortest = vec![None; grammar.rules().len()];
+    let mut done = false;
+    while !done {
+        done = true;
+        for (j, (A, alt)) in grammar.productions().into_iter().enumerate() {
+            let finished = alt.iter().all(|&symbol| match symbol {
+                Symbol::Terminal(_) => true,
+                Symbol::Variable(B) => shortest[B].is_some(),
+            });
+            if shortest[A].is_none() && finished && production_len(alt, min_lens) == min_lens[A] {
+                shortest[A] = Some(j);
+                done = false;
+            }
+        }
+    }
+    shortest
+}
+
+/// For each variable reachable from `var` through productive productions, a
+/// shortest chain of steps leading there.
+fn paths_from(grammar: &Grammar, var: usize, min_lens: &[usize]) -> Vec<Option<Vec<Step>>> {
+    let mut paths: Vec<Option<Vec<Step>>> = vec![None; grammar.rules().len()];
+    let mut queue = VecDeque::from([var]);
+    paths[var] = Some(Vec::new());
+
+    while let Some(A) = queue.pop_front() {
+        let rule = grammar.rules().get(A);
+        for (j, alt) in rule.production_ids().zip(rule.alts()) {
+            if production_len(alt, min_lens) == usize::MAX {
+                continue;
+            }
+            for (pos, &symbol) in alt.iter().enumerate() {
+                if let Symbol::Variable(B) = symbol {
+                    if paths[B].is_none() {
+                        let mut path = paths[A].clone().unwrap();
+                        path.push(Step { production: j, pos });
+                        paths[B] = Some(path);
+                        queue.push_back(B);
+                    }
+                }
+            }
+        }
+    }
+
+    paths
+}
+
+/// Derives from `var`, following `path` down to the left-hand side of
+/// `target` and applying it there, and shortest productions elsewhere.
+fn derive_along(
+    grammar: &Grammar,
+    shortest: &[Option<usize>],
+    var: usize,
+    path: &[Step],
+    target: Option<usize>,
+    derivation: &mut Derivation,
+) {
+    let (j, next) = match (path.split_first(), target) {
+        (Some((step, rest)), _) => (step.production, Some((step.pos, rest))),
+        (None, Some(target)) => (target, None),
+        (None, None) => (shortest[var].unwrap(), None),
+    };
+
+    derivation.productions.push(j);
+    for (pos, &symbol) in grammar.productions().get(j).1.iter().enumerate() {
+        match symbol {
+            Symbol::Terminal(a) => derivation.sentence.push(a),
+            Symbol::Variable(B) => match next {
+                Some((next_pos, rest)) if next_pos == pos => {
+                    derive_along(grammar, shortest, B, rest, target, derivation);
+                },
+                _ => derive_along(grammar, shortest, B, &[], None, derivation),
+            },
+        }
+    }
+}
@@ -1,6 +1,15 @@
-use super::{Nullable, First, Follow};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use super::{Alphabet, Ambiguity, Describe, Names, Nullable, First, Follow, FollowExplanation, Hygiene, ReducedGrammar, Recursion, Step};
+use super::ambiguity::find_ambiguity;
+use super::first::explain_first;
+use super::follow::explain_follow;
+use super::recursion::find_recursion;
+
+/// Symbols are ordered with all terminals before all variables, and by
+/// index otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Symbol {
     /// TODO: doc
     Terminal(usize),
@@ -16,6 +25,9 @@ pub struct Grammar {
     symbols: Vec<Symbol>, // flattened array of symbols that occur in RHS of productions
     alts:    Vec<usize>,  // indices into `symbols` for production j are given as (alts[j]..alts[j+1])
     rules:   Vec<usize>,  // indices into `alts` for rule i are given as (rules[i]..rules[i+1])
+    entry_points: Vec<usize>,
+    alphabet: Alphabet,
+    alphabet_declared: bool, // or else inferred from the terminals used
 }
 
 pub struct RuleView<'a> {
@@ -49,12 +61,45 @@ pub struct Alternatives<'a> {
 }
 
 impl Grammar {
+    /// The number of terminals, i.e. the length of the
+    /// [`alphabet`](Grammar::alphabet).
     #[must_use]
     pub fn word_count(&self) -> usize {
-        self.symbols.iter()
-            .filter_map(|symbol| if let Symbol::Terminal(word) = symbol { Some(*word) } else { None })
-            .max()
-            .map_or(0, |word| word + 1)
+        self.alphabet.len()
+    }
+
+    /// The terminals, as declared with [`GrammarBuilder::set_alphabet`], or
+    /// else unnamed tokens up to the greatest terminal occurring in a
+    /// production.
+    #[must_use]
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// Whether the alphabet was declared rather than inferred.
+    pub(super) fn alphabet_declared(&self) -> bool {
+        self.alphabet_declared
+    }
+
+    /// The variables parsing may start from, `[0]` unless set otherwise by
+    /// [`GrammarBuilder::entry_points`]. Each is augmented with a start rule
+    /// deriving it, and these rules come last, in order.
+    #[must_use]
+    pub fn entry_points(&self) -> &[usize] {
+        &self.entry_points
+    }
+
+    /// The augmented start variable of entry point `entry`.
+    #[must_use]
+    pub fn start_var(&self, entry: usize) -> usize {
+        self.rules().len() - self.entry_points.len() + entry
+    }
+
+    /// The single production of the augmented start variable of entry point
+    /// `entry`.
+    #[must_use]
+    pub fn start_production(&self, entry: usize) -> usize {
+        self.productions().len() - self.entry_points.len() + entry
     }
 
     #[must_use]
@@ -83,6 +128,61 @@ impl Grammar {
         let (first, nullable) = self.first_set();
         (Follow::new(self, &nullable, &first), first, nullable)
     }
+
+    /// A shortest chain of steps by which `var` derives a sentence beginning
+    /// with `word`, the last step being at `word` itself, or `None` if `word`
+    /// is not in the first set of `var`.
+    #[must_use]
+    pub fn explain_first(&self, var: usize, word: usize) -> Option<Vec<Step>> {
+        explain_first(self, &self.nullability(), var, word)
+    }
+
+    /// Why `word`, or EOF if `None`, is in the follow set of `var`, or `None`
+    /// if it is not.
+    #[must_use]
+    pub fn explain_follow(&self, var: usize, word: Option<usize>) -> Option<FollowExplanation> {
+        let (first, nullable) = self.first_set();
+        explain_follow(self, &nullable, &first, var, word)
+    }
+
+    /// Reports unproductive and unreachable symbols, unused terminals and
+    /// duplicate productions.
+    #[must_use]
+    pub fn hygiene(&self) -> Hygiene {
+        Hygiene::new(self)
+    }
+
+    /// Finds derivation cycles and hidden left recursion. Either makes LR
+    /// table construction fail with conflicts that are hard to trace back to
+    /// the grammar. Table construction does not call this: run it yourself
+    /// to explain such conflicts, as `lrtool check` does.
+    #[must_use]
+    pub fn recursion(&self) -> Vec<Recursion> {
+        find_recursion(self, &self.nullability())
+    }
+
+    /// Searches for a sentence of at most `max_len` terminals with two
+    /// derivation trees from the same entry point, returning the shortest
+    /// one found. Finding none does not prove the grammar unambiguous, but
+    /// unlike conflicts, finding one does prove it ambiguous.
+    ///
+    /// Only entry points are checked: an ambiguous variable that no entry
+    /// point reaches is never reported.
+    ///
+    /// The search keeps every sentence up to the current length for every
+    /// variable, so its cost grows exponentially with `max_len`. It gives up
+    /// at the last length completed once it holds about 65 000 sentences,
+    /// which bounds its memory whatever `max_len` is.
+    #[must_use]
+    pub fn ambiguity(&self, max_len: usize) -> Option<Ambiguity> {
+        find_ambiguity(self, max_len)
+    }
+
+    /// Removes useless symbols and duplicate productions; see [`ReducedGrammar`].
+    #[must_use]
+    pub fn reduce(&self) -> ReducedGrammar {
+        ReducedGrammar::new(self)
+    }
 }
 
 impl<'a> IntoIterator for RuleView<'a> {
@@ -198,14 +298,121 @@ impl<'a> Iterator for Alternatives<'a> {
     }
 }
 
+/// Builds a [`Grammar`], either by chaining the consuming methods, which
+/// add productions to the last rule started, or through the `&mut self`
+/// methods, which add them to any rule by its [`RuleHandle`]. Misuse, such
+/// as a production added before any rule, is reported by
+/// [`build`](GrammarBuilder::build) rather than by panicking.
 pub struct GrammarBuilder {
-    grammar: Grammar,
+    id:           usize,                 // distinguishes the handles of different builders
+    rules:        Vec<Vec<Vec<Symbol>>>, // productions of rule i, in order
+    names:        Vec<Option<String>>,   // names of the rules declared by name
+    entry_points: Vec<usize>,
+    alphabet:     Option<Alphabet>,
+    allow_empty:  bool,
+    misuse:       Option<GrammarBuildError>, // the first misuse, reported by `build`
+}
+
+/// A rule of a [`GrammarBuilder`], which productions can be added to and
+/// refer to before it is defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RuleHandle {
+    builder: usize,
+    var: usize,
 }
 
 #[derive(Debug)]
 pub enum GrammarBuildError {
     InvalidVariable { rule: usize, production: usize, pos: usize, variable: usize },
-} 
+    /// An applied template does not exist.
+    InvalidTemplate {
+        /// The template applied.
+        template: usize,
+    },
+    /// A template is applied to the wrong number of arguments.
+    InvalidArity {
+        /// The template applied.
+        template: usize,
+        /// Its number of parameters.
+        expected: usize,
+        /// The number of arguments given.
+        found: usize,
+    },
+    /// A parameter is out of range for its template, or used outside one.
+    InvalidParameter {
+        /// The parameter used.
+        param: usize,
+    },
+    /// Instantiating a template requires ever more instantiations.
+    UnboundedExpansion {
+        /// The template at which expansion was cut off.
+        template: usize,
+    },
+    /// An entry point does not exist.
+    InvalidEntryPoint {
+        /// The variable given as entry point.
+        variable: usize,
+    },
+    /// A terminal is not in the declared alphabet.
+    InvalidTerminal {
+        /// The rule of the production.
+        rule: usize,
+        /// The index of the production among those of the rule.
+        production: usize,
+        /// The position of the terminal in the production.
+        pos: usize,
+        /// The terminal used.
+        terminal: usize,
+        /// The number of terminals declared.
+        word_count: usize,
+    },
+    /// A rule has no productions, e.g. it was declared but never defined.
+    EmptyRule {
+        /// The rule.
+        rule: usize,
+    },
+    /// A production was added before any rule was started.
+    NoRule,
+    /// A rule handle does not belong to the builder it was used with.
+    InvalidHandle {
+        /// The variable of the handle.
+        variable: usize,
+    },
+}
+
+impl Describe for GrammarBuildError {
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::InvalidVariable { rule, production, pos, variable } => write!(f,
+                "alternative {} of `{}` refers to undefined variable {} at position {}",
+                production, names.variable(rule), variable, pos),
+            Self::InvalidTemplate { template } => write!(f, "template {} does not exist", template),
+            Self::InvalidArity { template, expected, found } => write!(f,
+                "template {} takes {} argument{}, but is applied to {}",
+                template, expected, if expected == 1 { "" } else { "s" }, found),
+            Self::InvalidParameter { param } => write!(f,
+                "parameter {} is out of range for its template, or used outside one", param),
+            Self::UnboundedExpansion { template } => write!(f,
+                "instantiating template {} requires ever more instantiations", template),
+            Self::InvalidEntryPoint { variable } => write!(f, "entry point {} is not a variable of the grammar", variable),
+            Self::InvalidTerminal { rule, production, pos, terminal, word_count } => write!(f,
+                "alternative {} of `{}` refers to terminal {} at position {}, but only {} are declared",
+                production, names.variable(rule), terminal, pos, word_count),
+            Self::EmptyRule { rule } => write!(f, "`{}` has no productions", names.variable(rule)),
+            Self::NoRule => write!(f, "a production was added before any rule"),
+            Self::InvalidHandle { variable } => write!(f,
+                "rule handle {} does not belong to this builder", variable),
+        }
+    }
+}
+
+impl fmt::Display for GrammarBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(&Names::default(), f)
+    }
+}
+
+impl Error for GrammarBuildError {}
 
 // consuming builder
 impl GrammarBuilder {
@@ -213,60 +420,247 @@ impl GrammarBuilder {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
-            grammar: Grammar {
-                lhs: Vec::new(),
-                symbols: Vec::new(),
-                alts: Vec::new(),
-                rules: Vec::new(),
-            },
+            id: NEXT_BUILDER_ID.fetch_add(1, Ordering::Relaxed),
+            rules: Vec::new(),
+            names: Vec::new(),
+            entry_points: Vec::new(),
+            alphabet: None,
+            allow_empty: false,
+            misuse: None,
         }
     }
 
     #[must_use]
     pub fn new_rule(mut self) -> Self {
-        self.grammar.rules.push(self.grammar.alts.len());
+        self.add_rule();
         self
     }
 
+    /// Adds a production to the last rule started.
     pub fn add_production<I: IntoIterator<Item = Symbol>>(mut self, symbols: I) -> Self {
-        self.grammar.lhs.push(self.grammar.rules.len() - 1);
-        self.grammar.alts.push(self.grammar.symbols.len());
-        self.grammar.symbols.extend(symbols);
+        match self.rules.last_mut() {
+            Some(rule) => rule.push(symbols.into_iter().collect()),
+            None => self.misuse(GrammarBuildError::NoRule),
+        }
+        self
+    }
+
+    /// Sets the variables parsing may start from, in place of variable `0`.
+    /// Entry point `i` is the `i`th variable given.
+    #[must_use]
+    pub fn entry_points<I: IntoIterator<Item = usize>>(mut self, vars: I) -> Self {
+        self.set_entry_points(vars);
+        self
+    }
+
+    /// Declares the number of terminals; see
+    /// [`set_word_count`](GrammarBuilder::set_word_count).
+    #[must_use]
+    pub fn word_count(mut self, count: usize) -> Self {
+        self.set_word_count(count);
+        self
+    }
+
+    /// Declares the terminals; see
+    /// [`set_alphabet`](GrammarBuilder::set_alphabet).
+    #[must_use]
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.set_alphabet(alphabet);
         self
     }
 
     /// # Errors
-    /// # Panics
-    pub fn build(mut self) -> Result<Grammar, GrammarBuildError> {
-        self.grammar.rules.push(self.grammar.alts.len());
-        self.grammar.alts.push(self.grammar.symbols.len());
+    /// Returns the first misuse of the builder, or else the first production
+    /// referring to an undefined variable or undeclared terminal, the first
+    /// rule without productions, unless allowed, or the first invalid entry
+    /// point.
+    pub fn build(self) -> Result<Grammar, GrammarBuildError> {
+        if let Some(misuse) = self.misuse {
+            return Err(misuse);
+        }
 
         // Iterates through each rule and checks to see
-        // if each variable is valid. If not, user receives 
+        // if each symbol is valid. If not, user receives
         // error corresponding to the first erroneous symbol.
-        for (i, rule) in self.grammar.rules().into_iter().enumerate() {
-            for (j, alt) in rule.alts().enumerate() {
+        let var_count = self.rules.len();
+        for (i, rule) in self.rules.iter().enumerate() {
+            for (j, alt) in rule.iter().enumerate() {
                 for (k, symbol) in alt.iter().enumerate() {
-                    if let Symbol::Variable(A) = symbol {
-                        if *A >= self.grammar.rules().len() { 
-                            return Err(GrammarBuildError::InvalidVariable {
+                    match *symbol {
+                        Symbol::Variable(A) if A >= var_count => {
+                            return Err(GrammarBuildError::InvalidVariable { rule: i, production: j, pos: k, variable: A });
+                        },
+                        Symbol::Terminal(a) => match &self.alphabet {
+                            Some(alphabet) if !alphabet.contains(a) => return Err(GrammarBuildError::InvalidTerminal {
                                 rule: i,
                                 production: j,
                                 pos: k,
-                                variable: *A,
-                            })
-                        }
+                                terminal: a,
+                                word_count: alphabet.len(),
+                            }),
+                            _ => {},
+                        },
+                        Symbol::Variable(_) => {},
                     }
                 }
             }
         }
 
-        // finally, we augment the grammar by adding a start rule
-        self.grammar.lhs.push(self.grammar.rules.len() - 1);
-        self.grammar.symbols.push(Symbol::Variable(0));
-        self.grammar.rules.push(self.grammar.alts.len());
-        self.grammar.alts.push(self.grammar.symbols.len());
+        if !self.allow_empty {
+            if let Some(A) = self.rules.iter().position(Vec::is_empty) {
+                return Err(GrammarBuildError::EmptyRule { rule: A });
+            }
+        }
+
+        let mut entry_points = self.entry_points;
+        if entry_points.is_empty() {
+            entry_points.push(0);
+        }
+        if let Some(&A) = entry_points.iter().find(|&&A| A >= var_count) {
+            return Err(GrammarBuildError::InvalidEntryPoint { variable: A });
+        }
+
+        let inferred = self.rules.iter().flatten().flatten()
+            .filter_map(|symbol| if let Symbol::Terminal(word) = symbol { Some(*word) } else { None })
+            .max()
+            .map_or(0, |word| word + 1);
+        let mut grammar = Grammar {
+            lhs: Vec::new(),
+            symbols: Vec::new(),
+            alts: Vec::new(),
+            rules: Vec::new(),
+            alphabet_declared: self.alphabet.is_some(),
+            alphabet: self.alphabet.unwrap_or_else(|| Alphabet::unnamed(inferred)),
+            entry_points,
+        };
+
+        // finally, we augment the grammar by adding a start rule per entry point
+        let start_rules = grammar.entry_points.iter().map(|&A| vec![vec![Symbol::Variable(A)]]);
+        for (A, rule) in self.rules.into_iter().chain(start_rules.collect::<Vec<_>>()).enumerate() {
+            grammar.rules.push(grammar.alts.len());
+            for alt in rule {
+                grammar.lhs.push(A);
+                grammar.alts.push(grammar.symbols.len());
+                grammar.symbols.extend(alt);
+            }
+        }
+        grammar.rules.push(grammar.alts.len());
+        grammar.alts.push(grammar.symbols.len());
+
+        Ok(grammar)
+    }
+}
+
+// non-consuming builder
+impl GrammarBuilder {
+    /// The rule named `name`, declared without productions if there is none
+    /// yet. Productions may refer to it before they are added to it, but
+    /// [`build`](GrammarBuilder::build) fails if it never gets any.
+    pub fn declare(&mut self, name: &str) -> RuleHandle {
+        self.lookup(name).unwrap_or_else(|| {
+            let rule = self.add_rule();
+            self.names[rule.var] = Some(name.to_string());
+            rule
+        })
+    }
+
+    /// Starts a new unnamed rule, like [`new_rule`](GrammarBuilder::new_rule).
+    pub fn add_rule(&mut self) -> RuleHandle {
+        self.rules.push(Vec::new());
+        self.names.push(None);
+        RuleHandle { builder: self.id, var: self.rules.len() - 1 }
+    }
+
+    /// The rule named `name`, if declared.
+    #[must_use]
+    pub fn lookup(&self, name: &str) -> Option<RuleHandle> {
+        self.names.iter()
+            .position(|rule_name| rule_name.as_deref() == Some(name))
+            .map(|var| RuleHandle { builder: self.id, var })
+    }
+
+    /// Adds a production to `rule`.
+    pub fn add<I: IntoIterator<Item = Symbol>>(&mut self, rule: RuleHandle, symbols: I) -> &mut Self {
+        match self.rules.get_mut(rule.var) {
+            Some(alts) if rule.builder == self.id => alts.push(symbols.into_iter().collect()),
+            _ => self.misuse(GrammarBuildError::InvalidHandle { variable: rule.var }),
+        }
+        self
+    }
+
+    /// Sets the variables parsing may start from, in place of variable `0`.
+    /// Entry point `i` is the `i`th variable given.
+    pub fn set_entry_points<I: IntoIterator<Item = usize>>(&mut self, vars: I) -> &mut Self {
+        self.entry_points = vars.into_iter().collect();
+        self
+    }
+
+    /// Declares `count` unnamed tokens as the alphabet.
+    pub fn set_word_count(&mut self, count: usize) -> &mut Self {
+        self.set_alphabet(Alphabet::unnamed(count))
+    }
+
+    /// Declares the terminals, so that terminals only the lexer produces are
+    /// part of the grammar, and terminals beyond them are errors. Otherwise
+    /// the alphabet is inferred from the terminals used.
+    pub fn set_alphabet(&mut self, alphabet: Alphabet) -> &mut Self {
+        self.alphabet = Some(alphabet);
+        self
+    }
 
-        Ok(self.grammar)
+    /// The token named `name`, added to the alphabet if not in it yet.
+    pub fn terminal(&mut self, name: &str) -> Symbol {
+        Symbol::Terminal(self.alphabet.get_or_insert_with(Alphabet::new).add(name))
     }
-}
\ No newline at end of file
+
+    /// Accepts rules without productions, which derive nothing.
+    pub fn allow_empty_rules(&mut self) -> &mut Self {
+        self.allow_empty = true;
+        self
+    }
+
+    /// The names of the rules and terminals declared so far, for describing
+    /// errors of [`build`](GrammarBuilder::build). Unnamed symbols are shown
+    /// by index.
+    #[must_use]
+    pub fn names(&self) -> Names {
+        let alphabet = self.alphabet.clone().unwrap_or_default();
+        let terminals = (0..alphabet.len())
+            .map(|a| alphabet.name(a).map_or_else(|| format!("t{}", a), str::to_string));
+        let variables = self.names.iter().enumerate()
+            .map(|(A, name)| name.clone().unwrap_or_else(|| format!("V{}", A)));
+        Names::new(terminals, variables)
+    }
+}
+
+impl RuleHandle {
+    /// The variable of the rule.
+    #[must_use]
+    pub fn var(self) -> usize {
+        self.var
+    }
+
+    /// The rule as a symbol, to use in productions.
+    #[must_use]
+    pub fn symbol(self) -> Symbol {
+        Symbol::Variable(self.var)
+    }
+}
+
+impl From<RuleHandle> for Symbol {
+    fn from(rule: RuleHandle) -> Self {
+        rule.symbol()
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+static NEXT_BUILDER_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl GrammarBuilder {
+    fn misuse(&mut self, error: GrammarBuildError) {
+        self.misuse.get_or_insert(error);
+    }
+}
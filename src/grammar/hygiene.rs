@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use super::{Grammar, GrammarBuilder, Symbol, TerminalKind};
+
+/// Useless symbols and redundant productions of a grammar.
+///
+/// The augmented start rules are never reported. Reachability is computed over
+/// all productions, so a symbol only reachable through unproductive ones is
+/// not reported as unreachable (but is still removed by
+/// [`reduce`](Grammar::reduce)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hygiene {
+    unproductive: Vec<usize>,
+    unreachable: Vec<Symbol>,
+    unused_terminals: Vec<usize>,
+    duplicates: Vec<(usize, usize)>,
+}
+
+/// A grammar with its useless symbols and duplicate productions removed,
+/// along with the mapping from old indices to new ones.
+///
+/// Entry points are always kept, even if unproductive, so that the reduced
+/// grammar has the same entry points. Terminals are not renumbered.
+#[derive(Debug, Clone)]
+pub struct ReducedGrammar {
+    grammar: Grammar,
+    variables: Vec<Option<usize>>,
+    productions: Vec<Option<usize>>,
+}
+
+impl Hygiene {
+    #[must_use]
+    pub(super) fn new(grammar: &Grammar) -> Self {
+        let var_count = grammar.start_var(0);
+        let productive = productive(grammar);
+        let (reachable_vars, reachable_words) = reachable(grammar, |_| true);
+
+        let unproductive = (0..var_count).filter(|&A| !productive[A]).collect();
+
+        let mut used_words = vec![false; grammar.word_count()];
+        for (_, alt) in grammar.productions() {
+            for &symbol in alt {
+                if let Symbol::Terminal(a) = symbol {
+                    used_words[a] = true;
+                }
+            }
+        }
+
+        let unreachable = (0..grammar.word_count())
+            .filter(|&a| used_words[a] && !reachable_words[a])
+            .map(Symbol::Terminal)
+            .chain((0..var_count).filter(|&A| !reachable_vars[A]).map(Symbol::Variable))
+            .collect();
+
+        let unused_terminals = (0..grammar.word_count())
+            .filter(|&a| !used_words[a] && grammar.alphabet().kind(a) == Some(TerminalKind::Token))
+            .collect();
+
+        Self {
+            unproductive,
+            unreachable,
+            unused_terminals,
+            duplicates: duplicates(grammar).into_iter().enumerate()
+                .filter_map(|(j, original)| original.map(|i| (j, i)))
+                .collect(),
+        }
+    }
+
+    /// Variables that derive no string of terminals.
+    #[must_use]
+    pub fn unproductive(&self) -> &[usize] {
+        &self.unproductive
+    }
+
+    /// Symbols occurring in the grammar that no sentential form derived from
+    /// an entry point contains, terminals first.
+    #[must_use]
+    pub fn unreachable(&self) -> &[Symbol] {
+        &self.unreachable
+    }
+
+    /// Tokens of the [`alphabet`](Grammar::alphabet) that occur in no
+    /// production. Skip and error terminals are not expected to.
+    #[must_use]
+    pub fn unused_terminals(&self) -> &[usize] {
+        &self.unused_terminals
+    }
+
+    /// Pairs `(j, i)` where production `j` repeats the earlier production `i`
+    /// of the same rule.
+    #[must_use]
+    pub fn duplicates(&self) -> &[(usize, usize)] {
+        &self.duplicates
+    }
+
+    /// Returns `true` if nothing was reported.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.unproductive.is_empty()
+            && self.unreachable.is_empty()
+            && self.unused_terminals.is_empty()
+            && self.duplicates.is_empty()
+    }
+}
+
+impl ReducedGrammar {
+    #[must_use]
+    pub(super) fn new(grammar: &Grammar) -> Self {
+        let var_count = grammar.start_var(0);
+        let start_production = grammar.start_production(0);
+        let productive = productive(grammar);
+        let duplicates = duplicates(grammar);
+
+        // A production is kept if all its variables are productive and it is
+        // not a duplicate. Reachability is then computed over kept
+        // productions only.
+        let keep_production: Vec<bool> = grammar.productions().into_iter().enumerate()
+            .map(|(j, (_, alt))| {
+                duplicates[j].is_none() && alt.iter().all(|&symbol| match symbol {
+                    Symbol::Terminal(_) => true,
+                    Symbol::Variable(B) => productive[B],
+                })
+            })
+            .collect();
+        let (reachable_vars, _) = reachable(grammar, |j| keep_production[j]);
+
+        let mut variables = vec![None; grammar.rules().len()];
+        let mut count = 0;
+        for A in 0..var_count {
+            if grammar.entry_points().contains(&A) || (productive[A] && reachable_vars[A]) {
+                variables[A] = Some(count);
+                count += 1;
+            }
+        }
+        for (entry, A) in (var_count..grammar.rules().len()).enumerate() {
+            variables[A] = Some(count + entry);
+        }
+
+        let mut productions = vec![None; grammar.productions().len()];
+        // An unproductive entry point is kept without productions.
+        // A declared alphabet is kept; an inferred one is inferred again.
+        let mut builder = GrammarBuilder::new();
+        builder.allow_empty_rules();
+        if grammar.alphabet_declared() {
+            builder.set_alphabet(grammar.alphabet().clone());
+        }
+        let mut count = 0;
+        for (A, rule) in grammar.rules().into_iter().enumerate().take(var_count) {
+            if variables[A].is_none() {
+                continue;
+            }
+            builder = builder.new_rule();
+            for (j, alt) in rule.production_ids().zip(rule.alts()) {
+                if keep_production[j] {
+                    builder = builder.add_production(alt.iter().map(|&symbol| match symbol {
+                        Symbol::Variable(B) => Symbol::Variable(variables[B].unwrap()),
+                        terminal => terminal,
+                    }));
+                    productions[j] = Some(count);
+                    count += 1;
+                }
+            }
+        }
+        for (entry, j) in (start_production..grammar.productions().len()).enumerate() {
+            productions[j] = Some(count + entry);
+        }
+        builder = builder.entry_points(grammar.entry_points().iter().map(|&A| variables[A].unwrap()));
+
+        Self {
+            grammar: builder.build().unwrap(),
+            variables,
+            productions,
+        }
+    }
+
+    /// The reduced grammar.
+    #[must_use]
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    /// Consumes the result, returning the reduced grammar.
+    #[must_use]
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    /// The index of variable `var` in the reduced grammar, if it was kept.
+    #[must_use]
+    pub fn variable(&self, var: usize) -> Option<usize> {
+        self.variables[var]
+    }
+
+    /// The index of production `production` in the reduced grammar, if it
+    /// was kept.
+    #[must_use]
+    pub fn production(&self, production: usize) -> Option<usize> {
+        self.productions[production]
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// For each variable (including the augmented start variables), whether it
+/// derives some string of terminals.
+fn productive(grammar: &Grammar) -> Vec<bool> {
+    let mut productive = vec![false; grammar.rules().len()];
+    let mut done = false;
+
+    while !done {
+        done = true;
+
+        for (A, rule) in grammar.rules().into_iter().enumerate() {
+            if !productive[A] {
+                productive[A] = rule.alts().any(|alt| {
+                    alt.iter().all(|&symbol| match symbol {
+                        Symbol::Terminal(_) => true,
+                        Symbol::Variable(B) => productive[B],
+                    })
+                });
+
+                if productive[A] {
+                    done = false;
+                }
+            }
+        }
+    }
+
+    productive
+}
+
+/// Computes the variables and terminals reachable from the augmented start
+/// variables through the productions accepted by `keep`.
+fn reachable<F: Fn(usize) -> bool>(grammar: &Grammar, keep: F) -> (Vec<bool>, Vec<bool>) {
+    let mut vars = vec![false; grammar.rules().len()];
+    let mut words = vec![false; grammar.word_count()];
+    let mut stack: Vec<usize> = (grammar.start_var(0)..grammar.rules().len()).collect();
+    for &A in &stack {
+        vars[A] = true;
+    }
+
+    while let Some(A) = stack.pop() {
+        let rule = grammar.rules().get(A);
+        for (j, alt) in rule.production_ids().zip(rule.alts()) {
+            if !keep(j) {
+                continue;
+            }
+            for &symbol in alt {
+                match symbol {
+                    Symbol::Terminal(a) => words[a] = true,
+                    Symbol::Variable(B) => if !vars[B] {
+                        vars[B] = true;
+                        stack.push(B);
+                    },
+                }
+            }
+        }
+    }
+
+    (vars, words)
+}
+
+/// For each production, the earliest production of the same rule with the
+/// same right-hand side, if it is not itself the earliest.
+fn duplicates(grammar: &Grammar) -> Vec<Option<usize>> {
+    let mut seen: HashMap<(usize, &[Symbol]), usize> = HashMap::new();
+    grammar.productions().into_iter().enumerate()
+        .map(|(j, production)| {
+            let i = *seen.entry(production).or_insert(j);
+            if i == j { None } else { Some(i) }
+        })
+        .collect()
+}
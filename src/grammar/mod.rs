@@ -7,18 +7,43 @@ pub use self::grammar::{
     Grammar,
     GrammarBuilder,
     GrammarBuildError,
+    RuleHandle,
     Symbol,
 };
 
+mod alphabet;
+pub use self::alphabet::{Alphabet, TerminalInfo, TerminalKind};
+
+mod ambiguity;
+pub use self::ambiguity::{Ambiguity, Tree};
+
+mod ebnf;
+pub use self::ebnf::{EbnfBuilder, EbnfGrammar, Expr, Synthetic};
+
 mod first;
 pub use self::first::First;
 
 mod follow;
-pub use self::follow::Follow;
+pub use self::follow::{Follow, FollowExplanation};
+
+mod hygiene;
+pub use self::hygiene::{Hygiene, ReducedGrammar};
+
+mod names;
+pub use self::names::{Describe, Named, Names};
 
 mod nullable;
 pub use self::nullable::Nullable;
 
+mod recursion;
+pub use self::recursion::{Recursion, Step};
+
+mod token_set;
+pub use self::token_set::{TokenSet, Tokens, Words};
+
+pub mod generate;
+pub mod transform;
+
 // =================
 // === INTERNALS ===
 // =================
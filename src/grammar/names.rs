@@ -0,0 +1,110 @@
+use std::fmt;
+use super::{Grammar, Symbol};
+
+/// Names for the symbols of a grammar, to make messages readable. Symbols
+/// without a name are shown by index, e.g. `t3` for terminal 3 and `V2` for
+/// variable 2.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Names {
+    terminals:   Vec<String>,
+    variables:   Vec<String>,
+    productions: Vec<String>, // rendered by `with_grammar`
+}
+
+/// Values, mainly errors, that can be described with the names of symbols.
+/// Their `Display` implementation uses the default [`Names`].
+pub trait Describe {
+    /// Writes a description, naming symbols with `names`.
+    ///
+    /// # Errors
+    /// Fails if the formatter does.
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Displays the value with `names`.
+    fn named<'a>(&'a self, names: &'a Names) -> Named<'a, Self> {
+        Named { value: self, names }
+    }
+}
+
+/// A value displayed with names, returned by [`Describe::named`].
+pub struct Named<'a, T: ?Sized> {
+    value: &'a T,
+    names: &'a Names,
+}
+
+impl Names {
+    /// Names terminals and variables in order of their indices.
+    pub fn new<T, V>(terminals: T, variables: V) -> Self
+    where
+        T: IntoIterator,
+        T::Item: Into<String>,
+        V: IntoIterator,
+        V::Item: Into<String>,
+    {
+        Self {
+            terminals: terminals.into_iter().map(Into::into).collect(),
+            variables: variables.into_iter().map(Into::into).collect(),
+            productions: Vec::new(),
+        }
+    }
+
+    /// Records the productions of `grammar`, so that messages can show them
+    /// rather than their index, and names the terminals not named yet after
+    /// its alphabet.
+    #[must_use]
+    pub fn with_grammar(mut self, grammar: &Grammar) -> Self {
+        for a in self.terminals.len()..grammar.word_count() {
+            let name = grammar.alphabet().name(a).map_or_else(|| format!("t{}", a), str::to_string);
+            self.terminals.push(name);
+        }
+        self.productions = (0..grammar.productions().len())
+            .map(|production| {
+                let (A, alt) = grammar.productions().get(production);
+                let symbols: Vec<String> = alt.iter().map(|&symbol| self.symbol(symbol)).collect();
+                let rhs = if symbols.is_empty() { "ε".to_string() } else { symbols.join(" ") };
+                format!("{} -> {}", self.variable(A), rhs)
+            })
+            .collect();
+        self
+    }
+
+    /// The name of terminal `word`.
+    #[must_use]
+    pub fn terminal(&self, word: usize) -> String {
+        self.terminals.get(word).cloned().unwrap_or_else(|| format!("t{}", word))
+    }
+
+    /// The name of variable `var`.
+    #[must_use]
+    pub fn variable(&self, var: usize) -> String {
+        self.variables.get(var).cloned().unwrap_or_else(|| format!("V{}", var))
+    }
+
+    /// The name of `symbol`.
+    #[must_use]
+    pub fn symbol(&self, symbol: Symbol) -> String {
+        match symbol {
+            Symbol::Terminal(a) => self.terminal(a),
+            Symbol::Variable(A) => self.variable(A),
+        }
+    }
+
+    /// The terminal quoted, or `end of input` for `None`.
+    #[must_use]
+    pub fn token(&self, token: Option<usize>) -> String {
+        token.map_or_else(|| "end of input".to_string(), |a| format!("`{}`", self.terminal(a)))
+    }
+
+    /// The production quoted, if the grammar is known, or else its index.
+    #[must_use]
+    pub fn production(&self, production: usize) -> String {
+        self.productions.get(production)
+            .map_or_else(|| format!("production {}", production), |text| format!("`{}`", text))
+    }
+}
+
+impl<T: Describe + ?Sized> fmt::Display for Named<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.describe(self.names, f)
+    }
+}
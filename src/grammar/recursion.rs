@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+use bit_vec::BitVec;
+use super::{Grammar, Nullable, Symbol};
+use crate::transitive_closure;
+
+/// One step of a derivation: the variable being rewritten is expanded with
+/// `production`, and the derivation continues with the symbol at `pos` in
+/// its right-hand side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// The production applied.
+    pub production: usize,
+    /// The position of the next symbol in the right-hand side.
+    pub pos: usize,
+}
+
+/// Problematic recursion found by [`Grammar::recursion`].
+///
+/// Each is reported once per strongly connected group of variables, for the
+/// lowest variable in the group, with a chain of steps leading from `var`
+/// back to itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recursion {
+    /// `A =>+ A`, where every step skips only nullable symbols on either
+    /// side. Such grammars are ambiguous.
+    Cycle {
+        /// The variable deriving itself.
+        var: usize,
+        /// The steps of the derivation.
+        chain: Vec<Step>,
+    },
+    /// `A =>+ pAq`, where `p` is nullable, and some step skips a nonempty
+    /// nullable prefix. Such grammars are not LR(k) for any `k`.
+    HiddenLeftRecursion {
+        /// The left recursive variable.
+        var: usize,
+        /// The steps of the derivation.
+        chain: Vec<Step>,
+    },
+}
+
+impl Recursion {
+    /// The variable deriving itself.
+    #[must_use]
+    pub fn var(&self) -> usize {
+        match self {
+            Self::Cycle { var, .. } | Self::HiddenLeftRecursion { var, .. } => *var,
+        }
+    }
+
+    /// The steps of the derivation from the variable back to itself.
+    #[must_use]
+    pub fn chain(&self) -> &[Step] {
+        match self {
+            Self::Cycle { chain, .. } | Self::HiddenLeftRecursion { chain, .. } => chain,
+        }
+    }
+}
+
+/// See [`Grammar::recursion`].
+pub(super) fn find_recursion(grammar: &Grammar, nullable: &Nullable) -> Vec<Recursion> {
+    let nullable_symbols = |symbols: &[Symbol]| symbols.iter().all(|&symbol| match symbol {
+        Symbol::Terminal(_) => false,
+        Symbol::Variable(B) => nullable.get(B),
+    });
+
+    let left_edges = edges(grammar, |alt, pos| nullable_symbols(&alt[..pos]));
+    let unit_edges = edges(grammar, |alt, pos| nullable_symbols(&alt[..pos]) && nullable_symbols(&alt[pos + 1..]));
+
+    let mut recursion = Vec::new();
+
+    let unit_reach = reach(&unit_edges);
+    for A in heads(&unit_reach) {
+        recursion.push(Recursion::Cycle {
+            var: A,
+            chain: cycle_through(&unit_edges, &unit_reach, A),
+        });
+    }
+
+    let left_reach = reach(&left_edges);
+    for A in heads(&left_reach) {
+        // The hidden step must stay within the group of A.
+        let in_group = |B: usize| left_reach[A][B] && left_reach[B][A];
+        let hidden = left_edges.iter().enumerate().find_map(|(X, edges)| {
+            edges.iter().find(|&&(step, Y)| step.pos > 0 && in_group(X) && in_group(Y)).map(|&edge| (X, edge))
+        });
+        if let Some((X, (step, Y))) = hidden {
+            let mut chain = path(&left_edges, A, X);
+            chain.push(step);
+            chain.extend(path(&left_edges, Y, A));
+            recursion.push(Recursion::HiddenLeftRecursion { var: A, chain });
+        }
+    }
+
+    recursion
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// For each variable `A`, the steps `(step, B)` from `A` to `B` accepted by
+/// `accept`, which is given the right-hand side and the position of `B`.
+fn edges<F: Fn(&[Symbol], usize) -> bool>(grammar: &Grammar, accept: F) -> Vec<Vec<(Step, usize)>> {
+    let mut edges = vec![Vec::new(); grammar.rules().len()];
+    for (production, (A, alt)) in grammar.productions().into_iter().enumerate() {
+        for (pos, &symbol) in alt.iter().enumerate() {
+            if let Symbol::Variable(B) = symbol {
+                if accept(alt, pos) {
+                    edges[A].push((Step { production, pos }, B));
+                }
+            }
+        }
+    }
+    edges
+}
+
+/// For each variable `A`, the variables `B` such that `A` reaches `B` in one
+/// or more steps.
+fn reach(edges: &[Vec<(Step, usize)>]) -> Vec<BitVec> {
+    let mut reach: Vec<BitVec> = edges.iter().map(|successors| {
+        let mut set = BitVec::from_elem(edges.len(), false);
+        for &(_, B) in successors {
+            set.set(B, true);
+        }
+        set
+    }).collect();
+    transitive_closure(&mut reach, |A| edges[A].iter().map(|&(_, B)| B), |a, b| { a.or(b); });
+    reach
+}
+
+/// The lowest variable of each group of mutually reachable variables.
+fn heads(reach: &[BitVec]) -> impl Iterator<Item = usize> + '_ {
+    (0..reach.len()).filter(move |&A| reach[A][A] && (0..A).all(|B| !(reach[A][B] && reach[B][A])))
+}
+
+/// A nonempty chain of steps from `A` back to itself.
+fn cycle_through(edges: &[Vec<(Step, usize)>], reach: &[BitVec], A: usize) -> Vec<Step> {
+    let &(step, B) = edges[A].iter()
+        .find(|&&(_, B)| B == A || reach[B][A])
+        .unwrap();
+    let mut chain = vec![step];
+    chain.extend(path(edges, B, A));
+    chain
+}
+
+/// A shortest chain of steps from `from` to `to`, which must be reachable.
+fn path(edges: &[Vec<(Step, usize)>], from: usize, to: usize) -> Vec<Step> {
+    let mut parent: Vec<Option<(usize, Step)>> = vec![None; edges.len()];
+    let mut queue = VecDeque::from(vec![from]);
+    let mut visited = BitVec::from_elem(edges.len(), false);
+    visited.set(from, true);
+
+    while let Some(A) = queue.pop_front() {
+        if A == to {
+            break;
+        }
+        for &(step, B) in &edges[A] {
+            if !visited[B] {
+                visited.set(B, true);
+                parent[B] = Some((A, step));
+                queue.push_back(B);
+            }
+        }
+    }
+
+    let mut chain = Vec::new();
+    let mut B = to;
+    while B != from {
+        let (A, step) = parent[B].unwrap();
+        chain.push(step);
+        B = A;
+    }
+    chain.reverse();
+    chain
+}
@@ -1,6 +1,7 @@
 #![allow(non_upper_case_globals)]
 
 use super::{Symbol, Grammar, GrammarBuilder};
+use super::transform::TransformError;
 
 fn rr_expr_grammar() -> Grammar {
     const add: Symbol    = Symbol::Terminal(0);
@@ -73,11 +74,11 @@ fn test_first() {
 
     let grammar = rr_expr_grammar();
     let (first, _) = grammar.first_set();
-    assert_eq!(&first.get(Expr), &[lparen, name, num]);
-    assert_eq!(&first.get(Expr_), &[add, sub]);
-    assert_eq!(&first.get(Term), &[lparen, name, num]);
-    assert_eq!(&first.get(Term_), &[mul, div]);
-    assert_eq!(&first.get(Factor), &[lparen, name, num]);
+    assert_eq!(first.get(Expr).words().collect::<Vec<_>>(), [lparen, name, num]);
+    assert_eq!(first.get(Expr_).words().collect::<Vec<_>>(), [add, sub]);
+    assert_eq!(first.get(Term).words().collect::<Vec<_>>(), [lparen, name, num]);
+    assert_eq!(first.get(Term_).words().collect::<Vec<_>>(), [mul, div]);
+    assert_eq!(first.get(Factor).words().collect::<Vec<_>>(), [lparen, name, num]);
     println!("first = {:?}", first);
 }
 
@@ -92,9 +93,724 @@ fn test_follow() {
 
     let grammar = rr_expr_grammar();
     let (follow, _, _) = grammar.follow_set();
-    assert_eq!(&follow.get(Expr), &[eof, rparen]);
-    assert_eq!(&follow.get(Expr_), &[eof, rparen]);
-    assert_eq!(&follow.get(Term), &[eof, add, sub, rparen]);
-    assert_eq!(&follow.get(Term_), &[eof, add, sub, rparen]);
-    assert_eq!(&follow.get(Factor), &[eof, add, sub, mul, div, rparen]);
-}
\ No newline at end of file
+    assert_eq!(follow.get(Expr).iter().collect::<Vec<_>>(), [eof, rparen]);
+    assert_eq!(follow.get(Expr_).iter().collect::<Vec<_>>(), [eof, rparen]);
+    assert_eq!(follow.get(Term).iter().collect::<Vec<_>>(), [eof, add, sub, rparen]);
+    assert_eq!(follow.get(Term_).iter().collect::<Vec<_>>(), [eof, add, sub, rparen]);
+    assert_eq!(follow.get(Factor).iter().collect::<Vec<_>>(), [eof, add, sub, mul, div, rparen]);
+}
+
+#[test]
+fn test_follow_through_cycles() {
+    // S : A c | B,
+    // A : a B | ,
+    // B : b A | C d,
+    // C : A,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Symbol::Variable(1), Symbol::Terminal(2)])
+                                                  .add_production([Symbol::Variable(2)])
+                                       .new_rule().add_production([Symbol::Terminal(0), Symbol::Variable(2)])
+                                                  .add_production([])
+                                       .new_rule().add_production([Symbol::Terminal(1), Symbol::Variable(1)])
+                                                  .add_production([Symbol::Variable(3), Symbol::Terminal(3)])
+                                       .new_rule().add_production([Symbol::Variable(1)])
+                                       .build().unwrap();
+
+    let (follow, _, _) = grammar.follow_set();
+    let follow = |var: usize| follow.get(var).iter().collect::<Vec<_>>();
+    assert_eq!(follow(0), [None]);
+    assert_eq!(follow(1), [None, Some(2), Some(3)]);
+    assert_eq!(follow(2), [None, Some(2), Some(3)]);
+    assert_eq!(follow(3), [Some(3)]);
+}
+
+#[test]
+fn test_first_of_and_explanations() {
+    use super::Step;
+
+    const add: usize    = 0;
+    const sub: usize    = 1;
+    const mul: usize    = 2;
+    const div: usize    = 3;
+    const lparen: usize = 4;
+    const rparen: usize = 5;
+    const name: usize   = 6;
+
+    let grammar = rr_expr_grammar();
+    let (first, nullable) = grammar.first_set();
+    let first_of = |symbols: &[Symbol]| {
+        let (words, nullable) = first.first_of(symbols, &nullable);
+        (words.words().collect::<Vec<_>>(), nullable)
+    };
+    assert_eq!(first_of(&[]), (vec![], true));
+    assert_eq!(first_of(&[Symbol::Variable(Term_), Symbol::Variable(Expr_)]), (vec![add, sub, mul, div], true));
+    assert_eq!(first_of(&[Symbol::Variable(Expr_), Symbol::Terminal(rparen)]), (vec![add, sub, rparen], false));
+
+    let step = |production, pos| Step { production, pos };
+
+    // Expr -> Term Expr_, Term -> Factor Term_, Factor -> ( Expr )
+    assert_eq!(grammar.explain_first(Expr, lparen), Some(vec![step(0, 0), step(4, 0), step(8, 0)]));
+    assert_eq!(grammar.explain_first(Expr_, name), None);
+
+    // Expr -> Term Expr_, Expr_ -> + Term Expr_
+    let explanation = grammar.explain_follow(Term, Some(add)).unwrap();
+    assert_eq!(explanation.occurrences(), [step(0, 0)]);
+    assert_eq!(explanation.first(), [step(0, 1), step(1, 0)]);
+
+    // Term -> Factor Term_, Expr -> Term Expr_, Factor -> ( Expr )
+    let explanation = grammar.explain_follow(Factor, Some(rparen)).unwrap();
+    assert_eq!(explanation.occurrences(), [step(4, 0), step(0, 0), step(8, 1)]);
+    assert_eq!(explanation.first(), [step(8, 2)]);
+
+    let explanation = grammar.explain_follow(Expr, None).unwrap();
+    assert_eq!(explanation.occurrences(), [step(grammar.start_production(0), 0)]);
+    assert!(explanation.first().is_empty());
+    assert_eq!(grammar.explain_follow(Expr, Some(name)), None);
+}
+
+#[test]
+fn test_hygiene_and_reduction() {
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // S : A a | B | A a,
+    // A : b | C,
+    // B : B c,
+    // C : d,
+    // D : e g,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(1), Word(0)])
+                                                  .add_production([Var(2)])
+                                                  .add_production([Var(1), Word(0)])
+                                       .new_rule().add_production([Word(1)])
+                                                  .add_production([Var(3)])
+                                       .new_rule().add_production([Var(2), Word(2)])
+                                       .new_rule().add_production([Word(3)])
+                                       .new_rule().add_production([Word(4), Word(6)])
+                                       .build().unwrap();
+
+    let hygiene = grammar.hygiene();
+    assert!(!hygiene.is_clean());
+    assert_eq!(hygiene.unproductive(), [2]);
+    assert_eq!(hygiene.unreachable(), [Word(4), Word(6), Var(4)]);
+    assert_eq!(hygiene.unused_terminals(), [5]);
+    assert_eq!(hygiene.duplicates(), [(2, 0)]);
+
+    let reduction = grammar.reduce();
+    let variables: Vec<_> = (0..6).map(|A| reduction.variable(A)).collect();
+    assert_eq!(variables, [Some(0), Some(1), None, Some(2), None, Some(3)]);
+    let productions: Vec<_> = (0..9).map(|j| reduction.production(j)).collect();
+    assert_eq!(productions, [Some(0), None, None, Some(1), Some(2), None, Some(3), None, Some(4)]);
+
+    let reduced = reduction.grammar();
+    assert_eq!(reduced.productions().get(2), (1, &[Var(2)][..]));
+    assert_eq!(reduced.productions().get(4), (3, &[Var(0)][..]));
+
+    let hygiene = reduced.hygiene();
+    assert!(hygiene.unproductive().is_empty());
+    assert!(hygiene.unreachable().is_empty());
+    assert!(hygiene.duplicates().is_empty());
+    assert_eq!(hygiene.unused_terminals(), [2]);
+}
+
+#[test]
+fn test_recursion() {
+    use super::{Recursion, Step};
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // S : B S a | b,
+    // B : | c,
+    // C : C D | d,
+    // D : | e,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(1), Var(0), Word(0)])
+                                                  .add_production([Word(1)])
+                                       .new_rule().add_production([])
+                                                  .add_production([Word(2)])
+                                       .new_rule().add_production([Var(2), Var(3)])
+                                                  .add_production([Word(3)])
+                                       .new_rule().add_production([])
+                                                  .add_production([Word(4)])
+                                       .build().unwrap();
+
+    assert_eq!(grammar.recursion(), [
+        Recursion::Cycle { var: 2, chain: vec![Step { production: 4, pos: 0 }] },
+        Recursion::HiddenLeftRecursion { var: 0, chain: vec![Step { production: 0, pos: 1 }] },
+    ]);
+    assert!(rr_expr_grammar().recursion().is_empty());
+}
+
+// === transform ===
+
+fn productions(grammar: &Grammar) -> Vec<(usize, Vec<Symbol>)> {
+    grammar.productions().into_iter().map(|(A, alt)| (A, alt.to_vec())).collect()
+}
+
+fn lr_expr_grammar() -> Grammar {
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // E : E + T | T,
+    // T : T * F | F,
+    // F : ( E ) | n,
+    GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Var(1)])
+                                    .add_production([Var(1)])
+                         .new_rule().add_production([Var(1), Word(1), Var(2)])
+                                    .add_production([Var(2)])
+                         .new_rule().add_production([Word(2), Var(0), Word(3)])
+                                    .add_production([Word(4)])
+                         .build().unwrap()
+}
+
+#[test]
+fn test_eliminate_left_recursion() {
+    use super::transform::eliminate_left_recursion;
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    let transformed = eliminate_left_recursion(&lr_expr_grammar()).unwrap();
+    assert_eq!(productions(transformed.grammar()), [
+        (0, vec![Var(1), Var(3)]),
+        (1, vec![Var(2), Var(4)]),
+        (2, vec![Word(2), Var(0), Word(3)]),
+        (2, vec![Word(4)]),
+        (3, vec![Word(0), Var(1), Var(3)]),
+        (3, vec![]),
+        (4, vec![Word(1), Var(2), Var(4)]),
+        (4, vec![]),
+        (5, vec![Var(0)]),
+    ]);
+    let origins: Vec<_> = (0..9).map(|j| transformed.production_origin(j)).collect();
+    assert_eq!(origins, [Some(1), Some(3), Some(4), Some(5), Some(0), None, Some(2), None, Some(6)]);
+    let origins: Vec<_> = (0..6).map(|A| transformed.variable_origin(A)).collect();
+    assert_eq!(origins, [Some(0), Some(1), Some(2), Some(0), Some(1), Some(3)]);
+    assert!(transformed.grammar().recursion().is_empty());
+
+    // S : A a | b,
+    // A : A c | S d | e,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(1), Word(0)])
+                                                  .add_production([Word(1)])
+                                       .new_rule().add_production([Var(1), Word(2)])
+                                                  .add_production([Var(0), Word(3)])
+                                                  .add_production([Word(4)])
+                                       .build().unwrap();
+    let transformed = eliminate_left_recursion(&grammar).unwrap();
+    assert_eq!(productions(transformed.grammar()), [
+        (0, vec![Var(1), Word(0)]),
+        (0, vec![Word(1)]),
+        (1, vec![Word(1), Word(3), Var(2)]),
+        (1, vec![Word(4), Var(2)]),
+        (2, vec![Word(2), Var(2)]),
+        (2, vec![Word(0), Word(3), Var(2)]),
+        (2, vec![]),
+        (3, vec![Var(0)]),
+    ]);
+
+    let transformed = eliminate_left_recursion(&rr_expr_grammar());
+    assert!(matches!(transformed, Err(TransformError::EpsilonProduction { production: 3 })));
+}
+
+#[test]
+fn test_left_factor() {
+    use super::transform::left_factor;
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // S : i E t S | i E t S e S | a,
+    // E : b,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Word(0), Var(1), Word(1), Var(0)])
+                                                  .add_production([Word(0), Var(1), Word(1), Var(0), Word(2), Var(0)])
+                                                  .add_production([Word(3)])
+                                       .new_rule().add_production([Word(4)])
+                                       .build().unwrap();
+    let transformed = left_factor(&grammar);
+    assert_eq!(productions(transformed.grammar()), [
+        (0, vec![Word(0), Var(1), Word(1), Var(0), Var(2)]),
+        (0, vec![Word(3)]),
+        (1, vec![Word(4)]),
+        (2, vec![]),
+        (2, vec![Word(2), Var(0)]),
+        (3, vec![Var(0)]),
+    ]);
+    let origins: Vec<_> = (0..6).map(|j| transformed.production_origin(j)).collect();
+    assert_eq!(origins, [None, Some(2), Some(3), Some(0), Some(1), Some(4)]);
+}
+
+#[test]
+fn test_remove_epsilon_and_unit() {
+    use super::transform::{remove_epsilon, remove_unit};
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // S : a S b | ,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Word(0), Var(0), Word(1)])
+                                                  .add_production([])
+                                       .build().unwrap();
+    let transformed = remove_epsilon(&grammar);
+    assert_eq!(productions(transformed.grammar()), [
+        (0, vec![Word(0), Var(1), Word(1)]),
+        (0, vec![Word(0), Word(1)]),
+        (0, vec![]),
+        (1, vec![Word(0), Var(1), Word(1)]),
+        (1, vec![Word(0), Word(1)]),
+        (2, vec![Var(0)]),
+    ]);
+    let origins: Vec<_> = (0..6).map(|j| transformed.production_origin(j)).collect();
+    assert_eq!(origins, [Some(0), Some(0), Some(1), Some(0), Some(0), Some(2)]);
+
+    let transformed = remove_unit(&lr_expr_grammar());
+    assert_eq!(productions(transformed.grammar()), [
+        (0, vec![Var(0), Word(0), Var(1)]),
+        (0, vec![Var(1), Word(1), Var(2)]),
+        (0, vec![Word(2), Var(0), Word(3)]),
+        (0, vec![Word(4)]),
+        (1, vec![Var(1), Word(1), Var(2)]),
+        (1, vec![Word(2), Var(0), Word(3)]),
+        (1, vec![Word(4)]),
+        (2, vec![Word(2), Var(0), Word(3)]),
+        (2, vec![Word(4)]),
+        (3, vec![Var(0)]),
+    ]);
+}
+
+#[test]
+fn test_chomsky_normal_form() {
+    use super::transform::chomsky_normal_form;
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // S : a S b | ,
+    let anbn = GrammarBuilder::new().new_rule().add_production([Word(0), Var(0), Word(1)])
+                                               .add_production([])
+                                    .build().unwrap();
+
+    for grammar in [rr_expr_grammar(), lr_expr_grammar(), anbn] {
+        let transformed = chomsky_normal_form(&grammar);
+        let cnf = transformed.grammar();
+        let start_production = cnf.productions().len() - 1;
+        let nullable_start = cnf.nullability().get(0);
+        for (j, (A, alt)) in cnf.productions().into_iter().enumerate().take(start_production) {
+            match alt {
+                [Symbol::Variable(B), Symbol::Variable(C)] => assert!(!nullable_start || (*B != 0 && *C != 0)),
+                [Symbol::Terminal(_)] => {},
+                [] => assert_eq!(A, 0),
+                _ => panic!("production {} is not in normal form: {:?}", j, alt),
+            }
+        }
+    }
+}
+
+// === ebnf ===
+
+fn list_grammar() -> super::EbnfGrammar {
+    use super::{EbnfBuilder, Expr};
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // List : [ sep_by(Item, ,) ],
+    // Item : n | ( n* ) | (a b | c) d? | n* d,
+    EbnfBuilder::new().new_rule().add_production([Word(0).into(), Expr::sep_by(Var(1), Word(2)), Word(1).into()])
+                      .new_rule().add_production([Word(3)])
+                                 .add_production([Word(4).into(), Expr::star(Word(3)), Word(5).into()])
+                                 .add_production([Expr::group([vec![Word(6), Word(7)], vec![Word(8)]]), Expr::optional(Word(9))])
+                                 .add_production([Expr::star(Word(3)), Word(9).into()])
+                      .build().unwrap()
+}
+
+#[test]
+fn test_ebnf_desugaring() {
+    use super::Synthetic;
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    let ebnf = list_grammar();
+    assert_eq!(productions(ebnf.grammar()), [
+        (0, vec![Word(0), Var(2), Word(1)]),
+        (1, vec![Word(3)]),
+        (1, vec![Word(4), Var(4), Word(5)]),
+        (1, vec![Var(5), Var(6)]),
+        (1, vec![Var(4), Word(9)]),
+        (2, vec![]),
+        (2, vec![Var(3)]),
+        (3, vec![Var(1)]),
+        (3, vec![Var(3), Word(2), Var(1)]),
+        (4, vec![]),
+        (4, vec![Var(4), Word(3)]),
+        (5, vec![Word(6), Word(7)]),
+        (5, vec![Word(8)]),
+        (6, vec![]),
+        (6, vec![Word(9)]),
+        (7, vec![Var(0)]),
+    ]);
+    let origins: Vec<_> = (0..8).map(|A| ebnf.origin(A)).collect();
+    assert_eq!(origins, [
+        None,
+        None,
+        Some(Synthetic::SepBy),
+        Some(Synthetic::SepBy1),
+        Some(Synthetic::Star),
+        Some(Synthetic::Group),
+        Some(Synthetic::Optional),
+        None,
+    ]);
+}
+
+#[test]
+fn test_ebnf_templates() {
+    use super::{EbnfBuilder, Expr, GrammarBuildError, Synthetic};
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // option(X) : | X,
+    // separated_nonempty_list(sep, X) : X | X sep separated_nonempty_list(sep, X),
+    // ---
+    // Args : ( separated_nonempty_list(,, Expr) ) | ( ),
+    // Expr : n | option(-) n | [ separated_nonempty_list(,, Expr) ] | { separated_nonempty_list(;, n) },
+    let (option, list) = (0, 1);
+    let ebnf = EbnfBuilder::new().new_template(1).add_production(Vec::<Expr>::new())
+                                                 .add_production([Expr::param(0)])
+                                 .new_template(2).add_production([Expr::param(1)])
+                                                 .add_production([Expr::param(1), Expr::param(0), Expr::apply(list, [Expr::param(0), Expr::param(1)])])
+                                 .new_rule().add_production([Word(0).into(), Expr::apply(list, [Word(2), Var(1)]), Word(1).into()])
+                                            .add_production([Word(0), Word(1)])
+                                 .new_rule().add_production([Word(3)])
+                                            .add_production([Expr::apply(option, [Word(4)]), Word(3).into()])
+                                            .add_production([Word(5).into(), Expr::apply(list, [Word(2), Var(1)]), Word(6).into()])
+                                            .add_production([Word(8).into(), Expr::apply(list, [Word(7), Word(3)]), Word(9).into()])
+                                 .build().unwrap();
+
+    assert_eq!(productions(ebnf.grammar()), [
+        (0, vec![Word(0), Var(2), Word(1)]),
+        (0, vec![Word(0), Word(1)]),
+        (1, vec![Word(3)]),
+        (1, vec![Var(3), Word(3)]),
+        (1, vec![Word(5), Var(2), Word(6)]),
+        (1, vec![Word(8), Var(4), Word(9)]),
+        (2, vec![Var(1)]),
+        (2, vec![Var(1), Word(2), Var(2)]),
+        (3, vec![]),
+        (3, vec![Word(4)]),
+        (4, vec![Word(3)]),
+        (4, vec![Word(3), Word(7), Var(4)]),
+        (5, vec![Var(0)]),
+    ]);
+    assert_eq!(ebnf.origin(2), Some(Synthetic::Template(list)));
+    assert_eq!(ebnf.origin(3), Some(Synthetic::Template(option)));
+    assert_eq!(ebnf.origin(4), Some(Synthetic::Template(list)));
+
+    // nested(X) : X | nested(X*),
+    let unbounded = EbnfBuilder::new().new_template(1).add_production([Expr::param(0)])
+                                                      .add_production([Expr::apply(0, [Expr::star(Expr::param(0))])])
+                                      .new_rule().add_production([Expr::apply(0, [Word(0)])])
+                                      .build();
+    assert!(matches!(unbounded, Err(GrammarBuildError::UnboundedExpansion { template: 0 })));
+
+    let arity = EbnfBuilder::new().new_template(2).add_production([Expr::param(0)])
+                                  .new_rule().add_production([Expr::apply(0, [Word(0)])])
+                                  .build();
+    assert!(matches!(arity, Err(GrammarBuildError::InvalidArity { template: 0, expected: 2, found: 1 })));
+
+    let param = EbnfBuilder::new().new_rule().add_production([Expr::param(0)]).build();
+    assert!(matches!(param, Err(GrammarBuildError::InvalidParameter { param: 0 })));
+
+    // Misuse is reported instead of panicking.
+    let misuse = EbnfBuilder::new().add_production([Word(0)]).new_rule().add_production([Word(0)]).build();
+    assert!(matches!(misuse, Err(GrammarBuildError::NoRule)));
+}
+
+#[test]
+fn test_entry_points() {
+    use super::GrammarBuildError;
+    use Symbol::{Terminal as Word, Variable as Var};
+
+    // S : A b,
+    // A : a,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(1), Word(1)])
+                                       .new_rule().add_production([Word(0)])
+                                       .entry_points([0, 1])
+                                       .build().unwrap();
+    assert_eq!(productions(&grammar)[2..], [(2, vec![Var(0)]), (3, vec![Var(1)])]);
+
+    let (follow, _, _) = grammar.follow_set();
+    assert_eq!(follow.get(1).iter().collect::<Vec<_>>(), [None, Some(1)]);
+
+    let reduction = grammar.reduce();
+    assert_eq!(reduction.grammar().entry_points(), [0, 1]);
+
+    let invalid = GrammarBuilder::new().new_rule().add_production([Word(0)]).entry_points([1]).build();
+    assert!(matches!(invalid, Err(GrammarBuildError::InvalidEntryPoint { variable: 1 })));
+}
+
+/// Replays the leftmost derivation `productions` from `var`.
+fn replay_leftmost(grammar: &Grammar, var: usize, productions: &[usize]) -> Vec<usize> {
+    let mut form = vec![Symbol::Variable(var)];
+    for &j in productions {
+        let (A, alt) = grammar.productions().get(j);
+        let pos = form.iter().position(|symbol| matches!(symbol, Symbol::Variable(_))).unwrap();
+        assert_eq!(form[pos], Symbol::Variable(A));
+        form.splice(pos..=pos, alt.iter().copied());
+    }
+    form.into_iter()
+        .map(|symbol| match symbol {
+            Symbol::Terminal(a) => a,
+            Symbol::Variable(_) => panic!("incomplete derivation"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_generate_sentences() {
+    use super::generate::{recognize, sentences};
+
+    let grammar = rr_expr_grammar();
+    assert_eq!(sentences(&grammar, 0, 1).collect::<Vec<_>>(), [vec![6], vec![7]]);
+
+    // Enumeration agrees with the recognizer over all strings up to length 4.
+    let mut strings = vec![Vec::new()];
+    let mut expected = Vec::new();
+    for _ in 0..=4 {
+        let mut next = Vec::new();
+        for string in strings {
+            if recognize(&grammar, 0, &string) {
+                expected.push(string.clone());
+            }
+            next.extend((0..grammar.word_count()).map(|a| {
+                let mut string = string.clone();
+                string.push(a);
+                string
+            }));
+        }
+        strings = next;
+    }
+    assert_eq!(sentences(&grammar, 0, 4).collect::<Vec<_>>(), expected);
+
+    // S : S S | a | ,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Symbol::Variable(0), Symbol::Variable(0)])
+                                                  .add_production([Symbol::Terminal(0)])
+                                                  .add_production([])
+                                       .build().unwrap();
+    assert_eq!(sentences(&grammar, 0, 3).collect::<Vec<_>>(), [vec![], vec![0], vec![0, 0], vec![0, 0, 0]]);
+}
+
+#[test]
+fn test_generate_samples_and_coverage() {
+    use super::generate::{coverage, near_misses, recognize, Sampler};
+
+    let grammar = rr_expr_grammar();
+
+    let mut sampler = Sampler::new(&grammar, 42).max_depth(8).max_len(20);
+    let samples: Vec<_> = (0..100).map(|_| sampler.sample(0).unwrap()).collect();
+    for sample in &samples {
+        assert!(sample.sentence().len() <= 20);
+        assert!(recognize(&grammar, 0, sample.sentence()));
+        assert_eq!(replay_leftmost(&grammar, Expr, sample.productions()), sample.sentence());
+    }
+    let mut sampler = Sampler::new(&grammar, 42).max_depth(8).max_len(20);
+    assert!(samples.iter().all(|sample| sampler.sample(0).as_ref() == Some(sample)));
+    assert!(Sampler::new(&grammar, 0).max_depth(2).sample(0).is_none());
+
+    let derivations = coverage(&grammar, 0);
+    let mut covered = vec![false; grammar.start_production(0)];
+    for derivation in &derivations {
+        assert_eq!(replay_leftmost(&grammar, Expr, derivation.productions()), derivation.sentence());
+        for &j in derivation.productions() {
+            covered[j] = true;
+        }
+    }
+    assert!(covered.iter().all(|&covered| covered));
+    assert!(derivations.len() < grammar.start_production(0));
+
+    // name + name
+    let misses = near_misses(&grammar, 0, &[6, 0, 6]);
+    assert!(misses.contains(&vec![6, 0]));
+    assert!(misses.contains(&vec![6, 6]));
+    assert!(misses.contains(&vec![0, 6, 6]));
+    assert!(!misses.contains(&vec![6]));
+    assert!(!misses.contains(&vec![6, 0, 7]));
+    assert!(misses.iter().all(|miss| !recognize(&grammar, 0, miss)));
+}
+
+#[test]
+fn test_ambiguity() {
+    use super::Tree;
+
+    let n = |production| Tree::Node { production, children: vec![Tree::Word(1)] };
+    let sum = |left, right| Tree::Node { production: 0, children: vec![left, Tree::Word(0), right] };
+
+    // E : E + E | n,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Symbol::Variable(0), Symbol::Terminal(0), Symbol::Variable(0)])
+                                                  .add_production([Symbol::Terminal(1)])
+                                       .build().unwrap();
+    assert_eq!(grammar.ambiguity(4), None);
+    let ambiguity = grammar.ambiguity(8).unwrap();
+    assert_eq!((ambiguity.entry(), ambiguity.sentence()), (0, &[1, 0, 1, 0, 1][..]));
+    let trees = ambiguity.trees();
+    assert_ne!(trees[0], trees[1]);
+    assert!(trees.contains(&sum(sum(n(1), n(1)), n(1))));
+    assert!(trees.contains(&sum(n(1), sum(n(1), n(1)))));
+    assert!(trees.iter().all(|tree| tree.sentence() == ambiguity.sentence()));
+
+    assert_eq!(rr_expr_grammar().ambiguity(5), None);
+    assert_eq!(lr_expr_grammar().ambiguity(5), None);
+
+    // S : i S | i S e S | a,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Symbol::Terminal(0), Symbol::Variable(0)])
+                                                  .add_production([Symbol::Terminal(0), Symbol::Variable(0), Symbol::Terminal(2), Symbol::Variable(0)])
+                                                  .add_production([Symbol::Terminal(1)])
+                                       .build().unwrap();
+    assert_eq!(grammar.ambiguity(6).unwrap().sentence(), [0, 0, 1, 2, 1]);
+
+    // A : A | a,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Symbol::Variable(0)])
+                                                  .add_production([Symbol::Terminal(0)])
+                                       .build().unwrap();
+    assert_eq!(grammar.ambiguity(3).unwrap().sentence(), [0]);
+
+    // S : S S | a | ,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Symbol::Variable(0), Symbol::Variable(0)])
+                                                  .add_production([Symbol::Terminal(0)])
+                                                  .add_production([])
+                                       .build().unwrap();
+    assert_eq!(grammar.ambiguity(3).unwrap().sentence(), []);
+
+    // L : w L | , for 64 terminals w, has 64^n sentences of length n; the
+    // search gives up instead of exhausting memory.
+    let mut builder = GrammarBuilder::new().new_rule().add_production([]);
+    for w in 0..64 {
+        builder = builder.add_production([Symbol::Terminal(w), Symbol::Variable(0)]);
+    }
+    let grammar = builder.build().unwrap();
+    assert_eq!(grammar.ambiguity(1000), None);
+    // ... but still finds an ambiguity short enough, here L : 0 0.
+    let mut builder = GrammarBuilder::new().new_rule().add_production([]);
+    for w in 0..64 {
+        builder = builder.add_production([Symbol::Terminal(w), Symbol::Variable(0)]);
+    }
+    let grammar = builder.add_production([Symbol::Terminal(0), Symbol::Terminal(0)]).build().unwrap();
+    assert_eq!(grammar.ambiguity(1000).unwrap().sentence(), [0, 0]);
+
+    // S : a, U : U U | b, where only the unreachable U is ambiguous.
+    let grammar = GrammarBuilder::new().new_rule().add_production([Symbol::Terminal(0)])
+                                       .new_rule().add_production([Symbol::Variable(1), Symbol::Variable(1)])
+                                                  .add_production([Symbol::Terminal(1)])
+                                       .build().unwrap();
+    assert_eq!(grammar.ambiguity(5), None);
+}
+
+#[test]
+fn test_error_messages() {
+    use super::{Describe, GrammarBuildError, Names};
+    use super::transform::eliminate_left_recursion;
+
+    let grammar = rr_expr_grammar();
+    let names = Names::new(vec!["+", "-", "*", "/", "(", ")", "name", "num"], vec!["Expr", "Expr'", "Term", "Term'", "Factor"])
+        .with_grammar(&grammar);
+    let error = eliminate_left_recursion(&grammar).err().unwrap();
+    assert_eq!(error.to_string(), "production 3 is an epsilon production");
+    assert_eq!(error.named(&names).to_string(), "`Expr' -> ε` is an epsilon production");
+
+    let error = GrammarBuilder::new().new_rule().add_production([Symbol::Variable(1)]).build().err().unwrap();
+    assert!(matches!(error, GrammarBuildError::InvalidVariable { .. }));
+    assert_eq!(error.to_string(), "alternative 0 of `V0` refers to undefined variable 1 at position 0");
+    assert_eq!(error.named(&Names::new(Vec::<String>::new(), vec!["Start"])).to_string(),
+        "alternative 0 of `Start` refers to undefined variable 1 at position 0");
+    let boxed: Box<dyn std::error::Error> = Box::new(error);
+    assert!(boxed.source().is_none());
+}
+
+#[test]
+fn test_builder_handles() {
+    use super::{Describe, GrammarBuildError};
+    use Symbol::Terminal as Word;
+
+    // list : list ',' item | item,
+    // item : num | '(' list ')',
+    let mut builder = GrammarBuilder::new();
+    let list = builder.declare("list");
+    let item = builder.declare("item");
+    builder.set_word_count(5);
+    builder.add(list, [list.symbol(), Word(0), item.symbol()])
+           .add(list, [item.symbol()]);
+    for word in [1, 3] {
+        builder.add(item, [Word(word)]);
+    }
+    builder.add(item, [Word(2), list.symbol(), Word(4)]);
+    assert_eq!(builder.declare("list"), list);
+    assert_eq!(builder.lookup("item").map(super::RuleHandle::var), Some(1));
+    assert_eq!(builder.lookup("expr"), None);
+
+    let grammar = builder.build().unwrap();
+    assert_eq!(grammar.rules().len(), 3);
+    assert_eq!(grammar.productions().get(1), (0, &[item.symbol()][..]));
+    assert_eq!(grammar.productions().get(2), (1, &[Word(1)][..]));
+    assert_eq!(grammar.word_count(), 5);
+    assert_eq!(GrammarBuilder::new().new_rule().add_production([Word(1)]).build().unwrap().word_count(), 2);
+
+    // A rule declared but never defined.
+    let mut builder = GrammarBuilder::new();
+    let start = builder.declare("start");
+    let rest = builder.declare("rest");
+    builder.add(start, [Word(0), rest.symbol()]);
+    let names = builder.names();
+    let error = builder.build().err().unwrap();
+    assert!(matches!(error, GrammarBuildError::EmptyRule { rule: 1 }));
+    assert_eq!(error.named(&names).to_string(), "`rest` has no productions");
+
+    let mut builder = GrammarBuilder::new();
+    let start = builder.add_rule();
+    builder.add(start, []).allow_empty_rules();
+    builder.add_rule();
+    assert!(builder.build().is_ok());
+
+    // Misuse is reported instead of panicking.
+    let error = GrammarBuilder::new().add_production([Word(0)]).new_rule().add_production([]).build().err().unwrap();
+    assert!(matches!(error, GrammarBuildError::NoRule));
+    let mut other = GrammarBuilder::new();
+    other.add_rule();
+    let foreign = other.add_rule();
+    let mut builder = GrammarBuilder::new();
+    builder.add_rule();
+    builder.add(foreign, []);
+    assert!(matches!(builder.build(), Err(GrammarBuildError::InvalidHandle { variable: 1 })));
+    let mut builder = GrammarBuilder::new();
+    builder.add_rule();
+    builder.add_rule();
+    builder.add(foreign, []);
+    assert!(matches!(builder.build(), Err(GrammarBuildError::InvalidHandle { variable: 1 })));
+
+    let error = GrammarBuilder::new().word_count(2).new_rule().add_production([Word(0), Word(2)]).build().err().unwrap();
+    assert_eq!(error.to_string(), "alternative 0 of `V0` refers to terminal 2 at position 1, but only 2 are declared");
+}
+
+#[test]
+fn test_alphabet() {
+    use super::{Alphabet, Names, TerminalKind};
+
+    let mut alphabet = Alphabet::new();
+    assert_eq!(alphabet.add("id"), 0);
+    assert_eq!(alphabet.add_kind("comment", TerminalKind::Skip), 1);
+    assert_eq!(alphabet.add("id"), 0);
+    assert_eq!(alphabet.lookup("comment"), Some(1));
+    assert_eq!(alphabet.kind(1), Some(TerminalKind::Skip));
+    assert_eq!(alphabet.name(2), None);
+    assert!(!alphabet.contains(2));
+
+    // Terminals declared through the builder extend its alphabet.
+    let mut builder = GrammarBuilder::new().alphabet(alphabet);
+    let list = builder.declare("list");
+    let comma = builder.terminal(",");
+    let id = builder.terminal("id");
+    builder.add(list, [list.symbol(), comma, id]).add(list, [id]);
+    let grammar = builder.build().unwrap();
+    assert_eq!(grammar.word_count(), 3);
+    assert_eq!(grammar.alphabet().name(2), Some(","));
+    assert!(grammar.hygiene().is_clean());
+    assert_eq!(grammar.reduce().grammar().word_count(), 3);
+
+    let names = Names::default().with_grammar(&grammar);
+    assert_eq!(names.production(0), "`V0 -> V0 , id`");
+
+    // Transforms keep a declared alphabet, but infer an inferred one again.
+    use super::transform::remove_unit;
+    let transformed = remove_unit(&grammar);
+    assert_eq!(transformed.grammar().alphabet(), grammar.alphabet());
+    let inferred = GrammarBuilder::new().new_rule().add_production([Symbol::Variable(1)])
+                                        .new_rule().add_production([Symbol::Terminal(0)])
+                                        .new_rule().add_production([Symbol::Terminal(1)])
+                                        .build().unwrap();
+    let transformed = remove_unit(&inferred);
+    assert_eq!(transformed.grammar().word_count(), 2);
+    assert_eq!(transformed.grammar().reduce().grammar().word_count(), 1);
+
+    // Terminals outside a declared alphabet are errors.
+    let error = GrammarBuilder::new().alphabet(Alphabet::unnamed(1)).new_rule().add_production([Symbol::Terminal(1)]).build();
+    assert!(matches!(error, Err(super::GrammarBuildError::InvalidTerminal { terminal: 1, word_count: 1, .. })));
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::iter::{Enumerate, Flatten};
+use bit_vec::BitVec;
+
+/// A dense set of tokens drawn from the alphabet of a grammar.
+///
+/// Tokens are represented as `Option<usize>`, where `None` represents EOF
+/// (End Of File). Internally, EOF occupies bit 0 and terminal `a` occupies
+/// bit `a + 1`, mirroring the column layout of the parse tables. Sets
+/// combined with one another must be created with the same `word_count`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenSet(BitVec);
+
+/// Iterator over the tokens of a [`TokenSet`] in ascending order (EOF first).
+pub struct Tokens<'a> {
+    bits: Enumerate<bit_vec::Iter<'a>>,
+}
+
+/// Iterator over the terminals of a [`TokenSet`] in ascending order.
+pub type Words<'a> = Flatten<Tokens<'a>>;
+
+impl TokenSet {
+    /// Creates an empty set able to hold EOF and terminals `0..word_count`.
+    #[must_use]
+    pub fn new(word_count: usize) -> Self {
+        Self(BitVec::from_elem(word_count + 1, false))
+    }
+
+    /// The number of terminals (excluding EOF) this set can hold.
+    #[must_use]
+    pub fn word_count(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    /// Returns `true` if the token was not already present.
+    pub fn insert(&mut self, token: Option<usize>) -> bool {
+        let index = token.map_or(0, |a| a + 1);
+        let present = self.0[index];
+        self.0.set(index, true);
+        !present
+    }
+
+    /// Returns `true` if the token is present.
+    #[must_use]
+    pub fn contains(&self, token: Option<usize>) -> bool {
+        self.0.get(token.map_or(0, |a| a + 1)).unwrap_or(false)
+    }
+
+    /// In-place union. Returns `true` if `self` changed.
+    pub fn union_with(&mut self, other: &Self) -> bool {
+        self.0.or(&other.0)
+    }
+
+    /// Returns `true` if every token in `self` is also in `other`.
+    #[must_use]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.0.blocks().zip(other.0.blocks()).all(|(a, b)| a & !b == 0)
+    }
+
+    /// Returns `true` if the set contains no tokens.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.none()
+    }
+
+    /// The number of tokens in the set.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.blocks().map(|block| block.count_ones() as usize).sum()
+    }
+
+    /// Iterates over all tokens, including EOF.
+    #[must_use]
+    pub fn iter(&self) -> Tokens<'_> {
+        Tokens { bits: self.0.iter().enumerate() }
+    }
+
+    /// Iterates over terminals only, skipping EOF.
+    pub fn words(&self) -> Words<'_> {
+        self.iter().flatten()
+    }
+}
+
+impl<'a> IntoIterator for &'a TokenSet {
+    type Item = Option<usize>;
+    type IntoIter = Tokens<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Option<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, bit) in &mut self.bits {
+            if bit {
+                return Some(index.checked_sub(1));
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Debug for TokenSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
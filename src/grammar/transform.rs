@@ -0,0 +1,447 @@
+//! Transformations producing equivalent grammars.
+//!
+//! Each transformation returns a [`Transformed`] grammar recording, for every
+//! variable and production of the result, where it came from in the input.
+//! Variables of the input keep their indices; variables introduced by a
+//! transformation are numbered after them.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use super::{Describe, Grammar, GrammarBuilder, Names, Recursion, Symbol};
+
+/// A grammar produced by a transformation, along with its provenance.
+#[derive(Debug, Clone)]
+pub struct Transformed {
+    grammar: Grammar,
+    variables: Vec<Option<usize>>,
+    productions: Vec<Option<usize>>,
+}
+
+/// Reasons a transformation cannot be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransformError {
+    /// The grammar has an epsilon production other than one for an entry
+    /// point occurring in no right-hand side. See [`remove_epsilon`].
+    EpsilonProduction {
+        /// The offending production.
+        production: usize,
+    },
+    /// The grammar has a derivation cycle or hidden left recursion. See
+    /// [`Grammar::recursion`].
+    Recursion(Recursion),
+}
+
+impl Describe for TransformError {
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EpsilonProduction { production } => write!(f,
+                "{} is an epsilon production", names.production(*production)),
+            Self::Recursion(Recursion::Cycle { var, .. }) => write!(f,
+                "`{}` derives itself", names.variable(*var)),
+            Self::Recursion(Recursion::HiddenLeftRecursion { var, .. }) => write!(f,
+                "`{}` is left recursive behind a nullable prefix", names.variable(*var)),
+        }
+    }
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(&Names::default(), f)
+    }
+}
+
+impl Error for TransformError {}
+
+impl Transformed {
+    /// The transformed grammar.
+    #[must_use]
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    /// Consumes the result, returning the transformed grammar.
+    #[must_use]
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    /// The input variable `var` stands for or was introduced on behalf of,
+    /// or `None` if it was introduced to stand for a terminal.
+    #[must_use]
+    pub fn variable_origin(&self, var: usize) -> Option<usize> {
+        self.variables[var]
+    }
+
+    /// The input production `production` was derived from, or `None` if it
+    /// was introduced by the transformation.
+    #[must_use]
+    pub fn production_origin(&self, production: usize) -> Option<usize> {
+        self.productions[production]
+    }
+
+    /// Composes with a transformation of the resulting grammar, so that
+    /// origins refer to the input of `self`.
+    #[must_use]
+    pub fn then(self, next: Transformed) -> Transformed {
+        Transformed {
+            grammar: next.grammar,
+            variables: next.variables.into_iter()
+                .map(|var| var.and_then(|var| self.variables[var]))
+                .collect(),
+            productions: next.productions.into_iter()
+                .map(|production| production.and_then(|production| self.productions[production]))
+                .collect(),
+        }
+    }
+}
+
+/// Eliminates direct and indirect left recursion.
+///
+/// Variables are substituted into the leading positions of the productions
+/// of later variables, after which direct left recursion `A -> Aa | b` is
+/// replaced by `A -> bA'` and `A' -> aA' | `.
+///
+/// # Errors
+/// Returns an error if the grammar has epsilon productions (remove them with
+/// [`remove_epsilon`] first), derivation cycles or hidden left recursion.
+pub fn eliminate_left_recursion(grammar: &Grammar) -> Result<Transformed, TransformError> {
+    let start_production = grammar.start_production(0);
+    let on_rhs = |A: usize| grammar.productions().into_iter()
+        .take(start_production)
+        .any(|(_, alt)| alt.contains(&Symbol::Variable(A)));
+    for (j, (A, alt)) in grammar.productions().into_iter().enumerate().take(start_production) {
+        if alt.is_empty() && (!grammar.entry_points().contains(&A) || on_rhs(A)) {
+            return Err(TransformError::EpsilonProduction { production: j });
+        }
+    }
+    if let Some(recursion) = grammar.recursion().into_iter().next() {
+        return Err(TransformError::Recursion(recursion));
+    }
+
+    let mut draft = Draft::new(grammar);
+    draft.eliminate_left_recursion();
+    Ok(draft.finish(grammar))
+}
+
+/// Factors out common prefixes, so that no two productions of a variable
+/// begin with the same symbol: `A -> ab | ac` becomes `A -> aA'` and
+/// `A' -> b | c`.
+#[must_use]
+pub fn left_factor(grammar: &Grammar) -> Transformed {
+    let mut draft = Draft::new(grammar);
+    draft.left_factor();
+    draft.finish(grammar)
+}
+
+/// Removes epsilon productions, adding a copy of each production with every
+/// combination of nullable variables omitted.
+///
+/// Nullable entry points keep a single epsilon production. Should such an
+/// entry point also occur in some right-hand side, those occurrences are
+/// replaced with a new variable deriving the same nonempty strings.
+#[must_use]
+pub fn remove_epsilon(grammar: &Grammar) -> Transformed {
+    let mut draft = Draft::new(grammar);
+    draft.remove_epsilon();
+    draft.finish(grammar)
+}
+
+/// Removes unit productions `A -> B`, giving `A` the non-unit productions of
+/// every variable it derives through unit productions alone.
+#[must_use]
+pub fn remove_unit(grammar: &Grammar) -> Transformed {
+    let mut draft = Draft::new(grammar);
+    draft.remove_unit();
+    draft.finish(grammar)
+}
+
+/// Converts to Chomsky normal form, where every production is `A -> BC` or
+/// `A -> a`, except for epsilon productions for nullable entry points.
+/// Useless symbols are not removed; see [`Grammar::reduce`].
+#[must_use]
+pub fn chomsky_normal_form(grammar: &Grammar) -> Transformed {
+    let mut draft = Draft::new(grammar);
+    draft.remove_epsilon();
+    draft.remove_unit();
+    draft.binarize();
+    draft.finish(grammar)
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// A mutable grammar without the augmented start rules, whose origins always
+/// refer to the grammar it was created from.
+struct Draft {
+    rules: Vec<Vec<Alt>>,
+    variables: Vec<Option<usize>>,
+    entry_points: Vec<usize>,
+}
+
+#[derive(Clone)]
+struct Alt {
+    symbols: Vec<Symbol>,
+    origin: Option<usize>,
+}
+
+impl Draft {
+    fn new(grammar: &Grammar) -> Self {
+        let var_count = grammar.start_var(0);
+        Self {
+            rules: grammar.rules().into_iter().take(var_count).map(|rule| {
+                rule.production_ids().zip(rule.alts()).map(|(j, alt)| Alt {
+                    symbols: alt.to_vec(),
+                    origin: Some(j),
+                }).collect()
+            }).collect(),
+            variables: (0..var_count).map(Some).collect(),
+            entry_points: grammar.entry_points().to_vec(),
+        }
+    }
+
+    fn finish(self, grammar: &Grammar) -> Transformed {
+        // A declared alphabet is kept; an inferred one is inferred again.
+        let mut builder = GrammarBuilder::new();
+        builder.allow_empty_rules();
+        if grammar.alphabet_declared() {
+            builder.set_alphabet(grammar.alphabet().clone());
+        }
+        let mut productions = Vec::new();
+        for rule in self.rules {
+            builder = builder.new_rule();
+            for alt in rule {
+                builder = builder.add_production(alt.symbols);
+                productions.push(alt.origin);
+            }
+        }
+
+        let mut variables = self.variables;
+        variables.extend((grammar.start_var(0)..grammar.rules().len()).map(Some));
+        productions.extend((grammar.start_production(0)..grammar.productions().len()).map(Some));
+
+        Transformed {
+            grammar: builder.entry_points(self.entry_points).build().unwrap(),
+            variables,
+            productions,
+        }
+    }
+
+    fn new_var(&mut self, origin: Option<usize>, alts: Vec<Alt>) -> usize {
+        self.rules.push(alts);
+        self.variables.push(origin);
+        self.rules.len() - 1
+    }
+
+    fn nullable(&self) -> Vec<bool> {
+        let mut nullable = vec![false; self.rules.len()];
+        let mut done = false;
+        while !done {
+            done = true;
+            for (A, rule) in self.rules.iter().enumerate() {
+                if !nullable[A] && rule.iter().any(|alt| alt.symbols.iter().all(|&symbol| match symbol {
+                    Symbol::Terminal(_) => false,
+                    Symbol::Variable(B) => nullable[B],
+                })) {
+                    nullable[A] = true;
+                    done = false;
+                }
+            }
+        }
+        nullable
+    }
+
+    fn eliminate_left_recursion(&mut self) {
+        for i in 0..self.rules.len() {
+            // Substitute earlier variables in leading positions until none
+            // remain. Earlier variables only lead with later ones, so this
+            // terminates.
+            while let Some(k) = self.rules[i].iter().position(|alt| {
+                matches!(alt.symbols.first(), Some(&Symbol::Variable(j)) if j < i)
+            }) {
+                let alt = self.rules[i].remove(k);
+                let j = if let Symbol::Variable(j) = alt.symbols[0] { j } else { unreachable!() };
+                let substituted: Vec<Alt> = self.rules[j].iter().map(|alt_j| Alt {
+                    symbols: alt_j.symbols.iter().chain(&alt.symbols[1..]).copied().collect(),
+                    origin: alt.origin,
+                }).collect();
+                self.rules[i].splice(k..k, substituted);
+            }
+
+            let (recursive, others): (Vec<Alt>, Vec<Alt>) = mem::take(&mut self.rules[i]).into_iter()
+                .partition(|alt| alt.symbols.first() == Some(&Symbol::Variable(i)));
+            if recursive.is_empty() {
+                self.rules[i] = others;
+                continue;
+            }
+
+            let A_ = self.rules.len();
+            let mut alts_: Vec<Alt> = recursive.into_iter().map(|alt| Alt {
+                symbols: alt.symbols[1..].iter().copied().chain([Symbol::Variable(A_)]).collect(),
+                origin: alt.origin,
+            }).collect();
+            alts_.push(Alt { symbols: Vec::new(), origin: None });
+            self.rules[i] = others.into_iter().map(|alt| Alt {
+                symbols: alt.symbols.into_iter().chain([Symbol::Variable(A_)]).collect(),
+                origin: alt.origin,
+            }).collect();
+            self.new_var(self.variables[i], alts_);
+        }
+    }
+
+    fn left_factor(&mut self) {
+        let mut A = 0;
+        while A < self.rules.len() {
+            while let Some(x) = common_first_symbol(&self.rules[A]) {
+                let group: Vec<usize> = (0..self.rules[A].len())
+                    .filter(|&k| self.rules[A][k].symbols.first() == Some(&x))
+                    .collect();
+                let prefix_len = (1..).find(|&len| {
+                    group.iter().any(|&k| {
+                        self.rules[A][k].symbols.get(len) != self.rules[A][group[0]].symbols.get(len)
+                            || self.rules[A][k].symbols.len() == len
+                    })
+                }).unwrap();
+                let prefix = self.rules[A][group[0]].symbols[..prefix_len].to_vec();
+
+                let A_ = self.rules.len();
+                let suffixes = group.iter().map(|&k| Alt {
+                    symbols: self.rules[A][k].symbols[prefix_len..].to_vec(),
+                    origin: self.rules[A][k].origin,
+                }).collect();
+
+                let rule = &mut self.rules[A];
+                rule[group[0]] = Alt {
+                    symbols: prefix.into_iter().chain([Symbol::Variable(A_)]).collect(),
+                    origin: None,
+                };
+                for &k in group[1..].iter().rev() {
+                    rule.remove(k);
+                }
+                self.new_var(self.variables[A], suffixes);
+            }
+            A += 1;
+        }
+    }
+
+    fn remove_epsilon(&mut self) {
+        let nullable = self.nullable();
+        let entry_epsilons: Vec<Option<usize>> = self.entry_points.iter()
+            .map(|&E| self.rules[E].iter().find(|alt| alt.symbols.is_empty()).and_then(|alt| alt.origin))
+            .collect();
+
+        for rule in &mut self.rules {
+            let mut seen = HashSet::new();
+            *rule = mem::take(rule).into_iter().flat_map(|alt| {
+                omissions(&alt.symbols, &nullable).into_iter().map(move |symbols| Alt {
+                    symbols,
+                    origin: alt.origin,
+                })
+            }).filter(|alt| !alt.symbols.is_empty() && seen.insert(alt.symbols.clone())).collect();
+        }
+
+        for (i, E) in self.entry_points.clone().into_iter().enumerate() {
+            if !nullable[E] || self.rules[E].iter().any(|alt| alt.symbols.is_empty()) {
+                continue;
+            }
+            let on_rhs = self.rules.iter().flatten().any(|alt| alt.symbols.contains(&Symbol::Variable(E)));
+            if on_rhs {
+                let E_ = self.new_var(self.variables[E], self.rules[E].clone());
+                for symbol in self.rules.iter_mut().flatten().flat_map(|alt| alt.symbols.iter_mut()) {
+                    if *symbol == Symbol::Variable(E) {
+                        *symbol = Symbol::Variable(E_);
+                    }
+                }
+            }
+            self.rules[E].push(Alt { symbols: Vec::new(), origin: entry_epsilons[i] });
+        }
+    }
+
+    fn remove_unit(&mut self) {
+        let rules = self.rules.clone();
+        for (A, rule) in self.rules.iter_mut().enumerate() {
+            let mut reached = vec![A];
+            let mut i = 0;
+            while i < reached.len() {
+                for alt in &rules[reached[i]] {
+                    if let [Symbol::Variable(B)] = alt.symbols[..] {
+                        if !reached.contains(&B) {
+                            reached.push(B);
+                        }
+                    }
+                }
+                i += 1;
+            }
+
+            let mut seen = HashSet::new();
+            *rule = reached.iter()
+                .flat_map(|&B| rules[B].iter())
+                .filter(|alt| !matches!(alt.symbols[..], [Symbol::Variable(_)]) && seen.insert(alt.symbols.clone()))
+                .cloned()
+                .collect();
+        }
+    }
+
+    /// Assumes epsilon and unit productions have been removed.
+    fn binarize(&mut self) {
+        let mut terminal_vars: HashMap<usize, usize> = HashMap::new();
+        for A in 0..self.rules.len() {
+            let mut rule = mem::take(&mut self.rules[A]);
+            for alt in &mut rule {
+                if alt.symbols.len() < 2 {
+                    continue;
+                }
+                for symbol in &mut alt.symbols {
+                    if let Symbol::Terminal(a) = *symbol {
+                        let T = *terminal_vars.entry(a).or_insert_with(|| {
+                            self.new_var(None, vec![Alt { symbols: vec![Symbol::Terminal(a)], origin: None }])
+                        });
+                        *symbol = Symbol::Variable(T);
+                    }
+                }
+                // A -> X1 ... Xn becomes A -> X1 ... X(n-2) Y and
+                // Y -> X(n-1) Xn, repeatedly.
+                while alt.symbols.len() > 2 {
+                    let tail = alt.symbols.split_off(alt.symbols.len() - 2);
+                    let Y = self.new_var(self.variables[A], vec![Alt { symbols: tail, origin: alt.origin }]);
+                    alt.symbols.push(Symbol::Variable(Y));
+                }
+            }
+            self.rules[A] = rule;
+        }
+    }
+}
+
+/// The first symbol, in order of appearance, that begins more than one
+/// alternative.
+fn common_first_symbol(alts: &[Alt]) -> Option<Symbol> {
+    let mut seen = HashSet::new();
+    alts.iter()
+        .filter_map(|alt| alt.symbols.first())
+        .find(|&&x| !seen.insert(x))
+        .copied()
+}
+
+/// Every copy of `symbols` with some subset of its nullable variables
+/// omitted, starting with `symbols` itself.
+fn omissions(symbols: &[Symbol], nullable: &[bool]) -> Vec<Vec<Symbol>> {
+    let mut result = vec![Vec::new()];
+    for &symbol in symbols {
+        let omit = matches!(symbol, Symbol::Variable(B) if nullable[B]);
+        let count = result.len();
+        if omit {
+            for k in 0..count {
+                let kept = result[k].iter().copied().chain([symbol]).collect();
+                let omitted = mem::replace(&mut result[k], kept);
+                result.push(omitted);
+            }
+        } else {
+            for copy in &mut result {
+                copy.push(symbol);
+            }
+        }
+    }
+    result
+}
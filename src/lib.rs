@@ -6,9 +6,14 @@ pub mod grammar;
 pub mod automata;
 pub mod table;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 // =================
 // === INTERNALS ===
 // =================
 
 mod transitive_closure;
-use transitive_closure::transitive_closure;
\ No newline at end of file
+use transitive_closure::transitive_closure;
+
+mod parallel;
\ No newline at end of file
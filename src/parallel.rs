@@ -0,0 +1,56 @@
+//! Helpers which run data-parallel loops on the rayon thread pool when the
+//! `parallel` feature is enabled, and sequentially otherwise. Results are
+//! always collected in input order, so callers observe the same output
+//! regardless of the number of threads.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// `Sync` when the `parallel` feature is enabled, otherwise no bound at all.
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// `Sync` when the `parallel` feature is enabled, otherwise no bound at all.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// `Send` when the `parallel` feature is enabled, otherwise no bound at all.
+#[cfg(feature = "parallel")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "parallel")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// `Send` when the `parallel` feature is enabled, otherwise no bound at all.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// Equivalent to `items.iter().map(f).collect()`.
+pub fn map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: MaybeSync,
+    U: MaybeSend,
+    F: Fn(&T) -> U + MaybeSync + MaybeSend,
+{
+    #[cfg(feature = "parallel")]
+    return items.par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    return items.iter().map(f).collect();
+}
+
+/// Equivalent to `(0..len).map(f).collect()`.
+pub fn map_range<U, F>(len: usize, f: F) -> Vec<U>
+where
+    U: MaybeSend,
+    F: Fn(usize) -> U + MaybeSync + MaybeSend,
+{
+    #[cfg(feature = "parallel")]
+    return (0..len).into_par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    return (0..len).map(f).collect();
+}
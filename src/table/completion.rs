@@ -0,0 +1,122 @@
+use super::{Action, LR1Table, ParseError};
+use super::parse::rejection;
+use crate::automata::{DottedItem, LRAutomaton};
+use crate::grammar::Grammar;
+
+/// The state of a parse after a prefix of the input, from which the words
+/// that may come next can be enumerated, e.g. to drive completion in an
+/// editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    stack: Vec<usize>,
+}
+
+impl Completion {
+    /// Runs `table` over `prefix` from entry point `entry`, stopping just
+    /// before the word following it.
+    ///
+    /// # Errors
+    /// Returns the first error, if the prefix cannot start a valid input.
+    ///
+    /// # Panics
+    /// Panics if `entry` is not an entry point of the grammar.
+    pub fn new<P, I, T, E, F>(table: &P, entry: usize, prefix: I, get_id: F) -> Result<Self, ParseError<E>>
+    where
+        P: LR1Table,
+        I: IntoIterator<Item=Result<T, E>>,
+        F: Fn(&T) -> usize,
+    {
+        let mut completion = Self { stack: vec![table.start_state(entry)] };
+        for (step, word) in prefix.into_iter().enumerate() {
+            let word = get_id(&word.map_err(ParseError::InputError)?);
+            let (base, pushed) = completion.simulate(table, Some(word))
+                .map_err(|state| rejection(table, step, state, Some(word)))?;
+            completion.stack.truncate(base);
+            completion.stack.extend(pushed);
+        }
+        Ok(completion)
+    }
+
+    /// The stack of states after the prefix, the current one last.
+    #[must_use]
+    pub fn states(&self) -> &[usize] {
+        &self.stack
+    }
+
+    /// The words, `None` for EOF, with which the prefix may continue; i.e.
+    /// those the parser would shift (or accept, for EOF) after the
+    /// reductions they call for. `grammar` must be the one `table` was
+    /// built from.
+    ///
+    /// Unlike looking at the actions of the current state alone, this is
+    /// exact for LALR(1) tables too, where a reduction may be taken on a
+    /// word that the state after it rejects.
+    #[must_use]
+    pub fn words<P: LR1Table>(&self, table: &P, grammar: &Grammar) -> Vec<Option<usize>> {
+        std::iter::once(None).chain((0..grammar.word_count()).map(Some))
+            .filter(|&word| self.simulate(table, word).is_ok())
+            .collect()
+    }
+
+    /// The variables whose productions are in progress, i.e. with the dot
+    /// past their start in a kernel item of a state on the stack, innermost
+    /// first and without repetition. `automaton` must be the one the table
+    /// was built from.
+    ///
+    /// A state may be shared by several contexts, so this may list
+    /// variables that the prefix could only be in progress of in another
+    /// context.
+    #[must_use]
+    pub fn in_progress<'a, A>(&self, automaton: &'a A) -> Vec<usize>
+    where
+        A: LRAutomaton<'a>,
+        <A::ItemSet as IntoIterator>::Item: DottedItem,
+    {
+        let grammar = automaton.grammar();
+        let var_count = grammar.start_var(0);
+        let mut vars = Vec::new();
+        for &state in self.stack.iter().rev() {
+            for item in automaton.items(state) {
+                let var = grammar.productions().get(item.production()).0;
+                if item.is_kernel_item() && item.pos() > 0 && var < var_count && !vars.contains(&var) {
+                    vars.push(var);
+                }
+            }
+        }
+        vars
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+impl Completion {
+    /// Runs the reductions `word` calls for, without modifying the stack.
+    /// Returns how many states of the stack are kept and the states pushed
+    /// on top of them, up to shifting the word, or the state rejecting it.
+    fn simulate<P: LR1Table>(&self, table: &P, word: Option<usize>) -> Result<(usize, Vec<usize>), usize> {
+        let mut base = self.stack.len(); // states kept from `self.stack`
+        let mut pushed = Vec::new();     // states pushed on top of them
+        loop {
+            let state = pushed.last().copied().unwrap_or(self.stack[base - 1]);
+            match table.action(state, word) {
+                Action::Invalid => return Err(state),
+                Action::Accept => break,
+                Action::Shift(next) => {
+                    pushed.push(next);
+                    break;
+                },
+                Action::Reduce(production) => {
+                    let reduction = table.reduction(production);
+                    let popped = reduction.count.min(pushed.len());
+                    pushed.truncate(pushed.len() - popped);
+                    base -= reduction.count - popped;
+                    let state = pushed.last().copied().unwrap_or(self.stack[base - 1]);
+                    pushed.push(table.goto(state, reduction.var).ok_or(state)?);
+                },
+            }
+        }
+        Ok((base, pushed))
+    }
+}
@@ -1,5 +1,7 @@
+use std::error::Error;
+use std::fmt;
 use super::{Action, NaiveLR1Table};
-use crate::grammar::Grammar;
+use crate::grammar::{Describe, Grammar, Names};
 
 #[derive(Debug)]
 pub struct ConstructionError {
@@ -11,4 +13,39 @@ pub struct ConstructionError {
 pub enum Conflict {
     ShiftReduce { word: usize, next_state: usize, production: usize },
     ReduceReduce { production1: usize, production2: usize },
-}
\ No newline at end of file
+}
+
+impl Describe for Conflict {
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::ShiftReduce { word, next_state, production } => write!(f,
+                "shift/reduce conflict on {}: shift to state {}, or reduce by {}",
+                names.token(Some(word)), next_state, names.production(production)),
+            Self::ReduceReduce { production1, production2 } => write!(f,
+                "reduce/reduce conflict between {} and {}",
+                names.production(production1), names.production(production2)),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(&Names::default(), f)
+    }
+}
+
+impl Error for Conflict {}
+
+impl Describe for ConstructionError {
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "state {} has a {}", self.state, self.conflict.named(names))
+    }
+}
+
+impl fmt::Display for ConstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(&Names::default(), f)
+    }
+}
+
+impl Error for ConstructionError {}
@@ -0,0 +1,280 @@
+use std::convert::Infallible;
+use std::ops::Range;
+use std::sync::Arc;
+use super::{Action, LR1Table, ParseError};
+use super::parse::rejection;
+
+/// A parse tree which can be updated after edits to its input, reusing the
+/// unaffected subtrees (Wagner and Graham). Every node records the state of
+/// the parser beneath it, which decides whether it can be reused as is.
+///
+/// Subtrees are shared between the trees before and after an edit. Only the
+/// nodes enclosing the edit are rebuilt, along with those the parser reaches
+/// in a different state, such as the spine of a left-recursive list after
+/// the edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTree<T> {
+    entry: usize,
+    root: Arc<SyntaxNode<T>>,
+}
+
+/// A node of a [`SyntaxTree`]: a word, or a variable with its children.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SyntaxNode<T> {
+    state: usize,
+    len: usize,
+    kind: NodeKind<T>,
+}
+
+impl<T: Clone> SyntaxTree<T> {
+    /// Parses `input` from entry point `entry`.
+    ///
+    /// # Errors
+    /// Returns the first error, as [`Parse`](super::Parse) would.
+    ///
+    /// # Panics
+    /// Panics if `entry` is not an entry point of the grammar.
+    pub fn parse<P, F>(table: &P, entry: usize, input: Vec<T>, get_id: F) -> Result<Self, ParseError<Infallible>>
+    where
+        P: LR1Table,
+        F: Fn(&T) -> usize,
+    {
+        let input = input.into_iter().rev().map(SyntaxNode::unshifted).collect();
+        Reparse { table, get_id, input, stack: Vec::new(), step: 0 }.run(entry)
+    }
+
+    /// Parses the input after replacing the words in `edit` by `words`,
+    /// giving the same tree as [`parse`](Self::parse) would. `table` must
+    /// be the one this tree was parsed with.
+    ///
+    /// # Errors
+    /// Returns [`InvalidEdit`](ParseError::InvalidEdit) if `edit` is not a
+    /// range of the input, or else the first error, as
+    /// [`parse`](Self::parse) would.
+    pub fn reparse<P, F>(&self, table: &P, edit: Range<usize>, words: Vec<T>, get_id: F) -> Result<Self, ParseError<Infallible>>
+    where
+        P: LR1Table,
+        F: Fn(&T) -> usize,
+    {
+        if edit.start > edit.end || edit.end > self.root.len {
+            return Err(ParseError::InvalidEdit { edit, len: self.root.len });
+        }
+
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        fragment(&self.root, 0, &edit, &mut before, &mut after);
+        let input = after.into_iter().rev()
+            .chain(words.into_iter().rev().map(SyntaxNode::unshifted))
+            .chain(before.into_iter().rev())
+            .collect();
+        Reparse { table, get_id, input, stack: Vec::new(), step: 0 }.run(self.entry)
+    }
+}
+
+impl<T> SyntaxTree<T> {
+    /// The entry point parsed from.
+    #[must_use]
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// The root, derived from the entry point.
+    #[must_use]
+    pub fn root(&self) -> &Arc<SyntaxNode<T>> {
+        &self.root
+    }
+}
+
+impl<T> SyntaxNode<T> {
+    /// The state beneath the node on the parse stack.
+    #[must_use]
+    pub fn state(&self) -> usize {
+        self.state
+    }
+
+    /// The number of words the node covers.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the node covers no words.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The word, if the node is a leaf.
+    #[must_use]
+    pub fn word(&self) -> Option<&T> {
+        match &self.kind {
+            NodeKind::Word(word) => Some(word),
+            NodeKind::Variable { .. } => None,
+        }
+    }
+
+    /// The variable and the production it was expanded with, if the node is
+    /// not a leaf.
+    #[must_use]
+    pub fn production(&self) -> Option<(usize, usize)> {
+        match &self.kind {
+            NodeKind::Word(_) => None,
+            NodeKind::Variable { var, production, .. } => Some((*var, *production)),
+        }
+    }
+
+    /// The children, none for a leaf.
+    #[must_use]
+    pub fn children(&self) -> &[Arc<SyntaxNode<T>>] {
+        match &self.kind {
+            NodeKind::Word(_) => &[],
+            NodeKind::Variable { children, .. } => children,
+        }
+    }
+
+    /// The words covered, from left to right.
+    #[must_use]
+    pub fn words(&self) -> Vec<&T> {
+        let mut words = Vec::with_capacity(self.len);
+        self.collect_words(&mut words);
+        words
+    }
+
+    fn collect_words<'a>(&'a self, words: &mut Vec<&'a T>) {
+        match &self.kind {
+            NodeKind::Word(word) => words.push(word),
+            NodeKind::Variable { children, .. } => for child in children {
+                child.collect_words(words);
+            },
+        }
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+#[derive(Debug, PartialEq, Eq)]
+enum NodeKind<T> {
+    Word(T),
+    Variable { var: usize, production: usize, children: Vec<Arc<SyntaxNode<T>>> },
+}
+
+impl<T> SyntaxNode<T> {
+    /// A word not shifted yet.
+    fn unshifted(word: T) -> Arc<Self> {
+        Arc::new(Self { state: usize::MAX, len: 1, kind: NodeKind::Word(word) })
+    }
+
+    /// The leftmost word.
+    fn first_word(&self) -> &T {
+        match &self.kind {
+            NodeKind::Word(word) => word,
+            NodeKind::Variable { children, .. } => children.iter().find(|child| !child.is_empty()).unwrap().first_word(),
+        }
+    }
+}
+
+/// Splits the subtrees of `node`, which starts at word `pos`, into those
+/// before and after `edit`, breaking down the nodes the edit affects. A
+/// node is affected if the edit touches its words or the word following it,
+/// which its last reductions depended on. Empty nodes are dropped, since the
+/// parser recreates them.
+fn fragment<T>(node: &Arc<SyntaxNode<T>>, pos: usize, edit: &Range<usize>, before: &mut Vec<Arc<SyntaxNode<T>>>, after: &mut Vec<Arc<SyntaxNode<T>>>) {
+    let end = pos + node.len;
+    if node.is_empty() {
+        return;
+    }
+    let affected = end >= edit.start && pos < edit.end;
+    match &node.kind {
+        NodeKind::Variable { children, .. } if affected => {
+            let mut child_pos = pos;
+            for child in children {
+                fragment(child, child_pos, edit, before, after);
+                child_pos += child.len;
+            }
+        },
+        _ if pos < edit.start => before.push(node.clone()),
+        _ if pos >= edit.end => after.push(node.clone()),
+        _ => {}, // a word replaced by the edit
+    }
+}
+
+/// The LR driver over a stream of subtrees.
+struct Reparse<'a, P, T, F> {
+    table: &'a P,
+    get_id: F,
+    input: Vec<Arc<SyntaxNode<T>>>,                 // the next subtree last
+    stack: Vec<(usize, Option<Arc<SyntaxNode<T>>>)>, // states, with the node each was reached by
+    step: usize,                                     // words shifted so far
+}
+
+impl<'a, P, T, F> Reparse<'a, P, T, F>
+where
+    P: LR1Table,
+    T: Clone,
+    F: Fn(&T) -> usize,
+{
+    fn run(mut self, entry: usize) -> Result<SyntaxTree<T>, ParseError<Infallible>> {
+        self.stack.push((self.table.start_state(entry), None));
+
+        loop {
+            let state = self.stack.last().unwrap().0;
+
+            // In the state it was built from, on the same words followed by
+            // the same word, the parser would rebuild the next subtree.
+            if let Some(next) = self.input.last() {
+                if let NodeKind::Variable { var, .. } = next.kind {
+                    if let Some(goto) = self.table.goto(state, var).filter(|_| next.state == state) {
+                        self.step += next.len;
+                        let next = self.input.pop();
+                        self.stack.push((goto, next));
+                        continue;
+                    }
+                }
+            }
+
+            let word = self.input.last().map(|next| (self.get_id)(next.first_word()));
+            match self.table.action(state, word) {
+                Action::Invalid => return Err(rejection(self.table, self.step, state, word)),
+                Action::Accept => {
+                    let root = self.stack.pop().unwrap().1.unwrap();
+                    return Ok(SyntaxTree { entry, root });
+                },
+                Action::Shift(next_state) => {
+                    let next = self.input.pop().unwrap();
+                    match &next.kind {
+                        NodeKind::Word(word) => {
+                            // Leaves are shared too, unless their state changed.
+                            let leaf = if next.state == state {
+                                next
+                            } else {
+                                Arc::new(SyntaxNode { state, len: 1, kind: NodeKind::Word(word.clone()) })
+                            };
+                            self.step += 1;
+                            self.stack.push((next_state, Some(leaf)));
+                        },
+                        NodeKind::Variable { children, .. } => {
+                            self.input.extend(children.iter().rev().filter(|child| !child.is_empty()).cloned());
+                        },
+                    }
+                },
+                Action::Reduce(production) => {
+                    let reduction = self.table.reduction(production);
+                    let children: Vec<_> = self.stack.drain(self.stack.len() - reduction.count..)
+                        .map(|(_, node)| node.unwrap())
+                        .collect();
+                    let state = self.stack.last().unwrap().0;
+                    let goto = self.table.goto(state, reduction.var)
+                        .ok_or(ParseError::InvalidGoto { step: self.step, state, var: reduction.var })?;
+                    let node = SyntaxNode {
+                        state,
+                        len: children.iter().map(|child| child.len).sum(),
+                        kind: NodeKind::Variable { var: reduction.var, production, children },
+                    };
+                    self.stack.push((goto, Some(Arc::new(node))));
+                },
+            }
+        }
+    }
+}
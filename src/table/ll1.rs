@@ -0,0 +1,249 @@
+#![allow(non_snake_case)]
+
+use std::error::Error;
+use std::fmt;
+use super::{Event, ParseError};
+use crate::grammar::{Describe, Grammar, Names, Symbol};
+
+/// Predictive parse table: for each variable and lookahead, the production
+/// to expand the variable with.
+#[derive(Debug)]
+pub struct LL1Table {
+    predictions:  Vec<Option<usize>>, // lookup which production to expand given var and word
+    productions:  Vec<(usize, Vec<Symbol>)>,
+    entry_points: Vec<usize>,
+    word_count:   usize,
+}
+
+/// Two productions of `var` predicted by the same lookahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LL1Conflict {
+    /// Both productions can begin with `word`.
+    FirstFirst {
+        /// The variable being expanded.
+        var: usize,
+        /// The lookahead.
+        word: usize,
+        /// The production predicted so far.
+        production1: usize,
+        /// The production conflicting with it.
+        production2: usize,
+    },
+    /// At least one of the productions is nullable and predicted because
+    /// `word` follows `var`.
+    FirstFollow {
+        /// The variable being expanded.
+        var: usize,
+        /// The lookahead, `None` for EOF.
+        word: Option<usize>,
+        /// The production predicted so far.
+        production1: usize,
+        /// The production conflicting with it.
+        production2: usize,
+    },
+}
+
+/// Predictive parse driver, emitting the same [`Event`]s as
+/// [`Parse`](super::Parse) would for the same tree.
+pub struct LL1Parse<'a, I, T, F> {
+    table:     &'a LL1Table,
+    input:     I,
+    get_id:    F,
+    step:      usize,
+    next_word: Option<Option<T>>, // `None` until read, `Some(None)` at EOF
+    stack:     Vec<Frame>,
+    done:      bool,
+}
+
+impl LL1Table {
+    /// Builds the table, calling `conflict_resolution` with each conflict,
+    /// in order of variable and lookahead. It returns the production to
+    /// predict, or the conflict to fail with.
+    ///
+    /// # Errors
+    /// Returns the first conflict that `conflict_resolution` fails to resolve,
+    /// or resolves to a production other than the two conflicting ones.
+    pub fn build<F>(grammar: &Grammar, mut conflict_resolution: F) -> Result<Self, LL1Conflict>
+    where
+        F: FnMut(LL1Conflict) -> Result<usize, LL1Conflict>,
+    {
+        let (follow, first, nullable) = grammar.follow_set();
+        let word_count = grammar.word_count() + 1; // +1 for eof
+        let var_count = grammar.start_var(0);
+
+        let mut table = Self {
+            predictions: vec![None; var_count * word_count],
+            productions: grammar.productions().into_iter().map(|(A, alt)| (A, alt.to_vec())).collect(),
+            entry_points: grammar.entry_points().to_vec(),
+            word_count,
+        };
+
+        for A in 0..var_count {
+            // For each column, the candidate productions, and whether each
+            // was predicted by its follow set.
+            let mut candidates: Vec<Vec<(usize, bool)>> = vec![Vec::new(); word_count];
+            let rule = grammar.rules().get(A);
+            for (j, alt) in rule.production_ids().zip(rule.alts()) {
+                let (firsts, nullable_alt) = first.first_of(alt, &nullable);
+
+                for a in firsts.words() {
+                    candidates[a + 1].push((j, false));
+                }
+                if nullable_alt {
+                    for token in follow.get(A) {
+                        let column = token.map_or(0, |a| a + 1);
+                        if !candidates[column].contains(&(j, false)) {
+                            candidates[column].push((j, true));
+                        }
+                    }
+                }
+            }
+
+            for (column, candidates) in candidates.into_iter().enumerate() {
+                let mut iter = candidates.into_iter();
+                let mut prediction = iter.next();
+                for (production2, follows2) in iter {
+                    let (production1, follows1) = prediction.unwrap();
+                    let conflict = if follows1 || follows2 {
+                        LL1Conflict::FirstFollow { var: A, word: column.checked_sub(1), production1, production2 }
+                    } else {
+                        LL1Conflict::FirstFirst { var: A, word: column - 1, production1, production2 }
+                    };
+                    let chosen = conflict_resolution(conflict)?;
+                    if chosen != production1 && chosen != production2 {
+                        return Err(conflict);
+                    }
+                    prediction = Some((chosen, if chosen == production2 { follows2 } else { follows1 }));
+                }
+                table.predictions[A * word_count + column] = prediction.map(|(j, _)| j);
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// The production to expand `var` with when `word` is next, if any.
+    #[must_use]
+    pub fn predict(&self, var: usize, word: Option<usize>) -> Option<usize> {
+        match word {
+            Some(a) if a >= self.word_count - 1 => None,
+            _ => self.predictions[var * self.word_count + word.map_or(0, |a| a + 1)],
+        }
+    }
+}
+
+impl Describe for LL1Conflict {
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::FirstFirst { var, word, production1, production2 } => write!(f,
+                "FIRST/FIRST conflict for `{}` on {}: both {} and {} can begin with it",
+                names.variable(var), names.token(Some(word)), names.production(production1), names.production(production2)),
+            Self::FirstFollow { var, word, production1, production2 } => write!(f,
+                "FIRST/FOLLOW conflict for `{}` on {}: both {} and {} are predicted, as it may follow `{}`",
+                names.variable(var), names.token(word), names.production(production1), names.production(production2), names.variable(var)),
+        }
+    }
+}
+
+impl fmt::Display for LL1Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(&Names::default(), f)
+    }
+}
+
+impl Error for LL1Conflict {}
+
+impl<'a, I, T, F> LL1Parse<'a, I, T, F>
+where
+    F: Fn(&T) -> usize,
+{
+    /// Parses `input` starting from entry point `entry`.
+    ///
+    /// # Panics
+    /// Panics if `entry` is not an entry point of the grammar.
+    #[must_use]
+    pub fn new(table: &'a LL1Table, entry: usize, input: I, get_id: F) -> Self {
+        let start = *table.entry_points.get(entry).unwrap_or_else(|| panic!("invalid entry point {}", entry));
+        Self {
+            table,
+            input,
+            get_id,
+            step:      0,
+            next_word: None,
+            stack:     vec![Frame::Expand(start)],
+            done:      false,
+        }
+    }
+}
+
+impl<'a, I, T, E, F> Iterator for LL1Parse<'a, I, T, F>
+where
+    I: Iterator<Item=Result<T, E>>,
+    F: Fn(&T) -> usize,
+{
+    type Item = Result<Event<T>, ParseError<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            if self.next_word.is_none() {
+                match self.input.next().transpose() {
+                    Ok(word) => self.next_word = Some(word),
+                    Err(err) => {
+                        self.done = true;
+                        return Some(Err(ParseError::InputError(err)));
+                    },
+                }
+            }
+            let word = self.next_word.as_ref().unwrap().as_ref().map(&self.get_id);
+
+            if let Some(a) = word.filter(|&a| a >= self.table.word_count - 1) {
+                self.done = true;
+                return Some(Err(ParseError::UnknownWord { step: self.step, word: a }));
+            }
+
+            let error = match self.stack.pop() {
+                None if word.is_none() => {
+                    self.done = true;
+                    return None;
+                },
+                None => ParseError::UnexpectedWord { step: self.step, expected: None, word },
+                Some(Frame::Reduce(production)) => {
+                    let (var, alt) = &self.table.productions[production];
+                    return Some(Ok(Event::Reduce { var: *var, child_count: alt.len(), production }));
+                },
+                Some(Frame::Shift(a)) if word == Some(a) => {
+                    self.step += 1;
+                    let word = self.next_word.take().unwrap().unwrap();
+                    return Some(Ok(Event::Shift(word)));
+                },
+                Some(Frame::Shift(a)) => ParseError::UnexpectedWord { step: self.step, expected: Some(a), word },
+                Some(Frame::Expand(var)) => {
+                    if let Some(production) = self.table.predict(var, word) {
+                        self.stack.push(Frame::Reduce(production));
+                        self.stack.extend(self.table.productions[production].1.iter().rev().map(|&symbol| match symbol {
+                            Symbol::Terminal(a) => Frame::Shift(a),
+                            Symbol::Variable(B) => Frame::Expand(B),
+                        }));
+                        continue;
+                    }
+                    ParseError::NoPrediction { step: self.step, var, word }
+                },
+            };
+
+            self.done = true;
+            return Some(Err(error));
+        }
+        None
+    }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// Pending work of the predictive parser, as a stack in reverse order.
+enum Frame {
+    Shift(usize),
+    Expand(usize),
+    Reduce(usize),
+}
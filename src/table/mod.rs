@@ -18,6 +18,42 @@ pub use self::parse::{
     ParseError,
 };
 
+mod span;
+pub use self::span::{
+    Span,
+    Spanned,
+    SpannedError,
+    SpannedParse,
+};
+
+mod trace;
+pub use self::trace::{
+    Trace,
+    TraceStep,
+};
+
+mod completion;
+pub use self::completion::Completion;
+
+mod incremental;
+pub use self::incremental::{
+    SyntaxNode,
+    SyntaxTree,
+};
+
+mod ll1;
+pub use self::ll1::{
+    LL1Conflict,
+    LL1Parse,
+    LL1Table,
+};
+
+mod parser;
+pub use self::parser::{
+    AutomatonKind,
+    Parser,
+};
+
 // =================
 // === INTERNALS ===
 // =================
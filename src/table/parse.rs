@@ -1,7 +1,11 @@
 #![allow(clippy::option_if_let_else)]
 
+use std::error::Error;
+use std::fmt;
 use std::mem;
-use super::{Action, LR1Table};
+use std::ops::Range;
+use super::{Action, LR1Table, Trace};
+use crate::grammar::{Describe, Names};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Event<T> {
@@ -17,6 +21,7 @@ pub struct Parse<'a, P, I, T, F> {
     next_word:     Option<T>,
     next_action:   Action,
     state_history: Vec<usize>,
+    trace:         Option<Trace>,
 }
 
 #[derive(Debug)]
@@ -24,6 +29,77 @@ pub enum ParseError<E> {
     InputError(E),
     InvalidAction { step: usize, state: usize, word: Option<usize> },
     InvalidGoto { step: usize, state: usize, var: usize },
+    /// The predictive parser expected a terminal, or EOF if `None`, but
+    /// found `word`.
+    UnexpectedWord {
+        /// The number of words shifted so far.
+        step: usize,
+        /// The terminal expected.
+        expected: Option<usize>,
+        /// The word found, `None` for EOF.
+        word: Option<usize>,
+    },
+    /// The predictive parser has no production to expand `var` with when
+    /// `word` is next.
+    NoPrediction {
+        /// The number of words shifted so far.
+        step: usize,
+        /// The variable being expanded.
+        var: usize,
+        /// The word found, `None` for EOF.
+        word: Option<usize>,
+    },
+    /// A word is not a terminal of the grammar, i.e. outside its alphabet.
+    UnknownWord {
+        /// The number of words shifted so far.
+        step: usize,
+        /// The word found.
+        word: usize,
+    },
+    /// The range of words to replace when reparsing is reversed or extends
+    /// past the end of the input.
+    InvalidEdit {
+        /// The range given.
+        edit: Range<usize>,
+        /// The number of words in the input.
+        len: usize,
+    },
+}
+
+impl<E> Describe for ParseError<E> {
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::InputError(_) => write!(f, "failed to read the input"),
+            Self::InvalidAction { step, state, word } => write!(f,
+                "unexpected {} at position {}, in state {}", names.token(word), step, state),
+            Self::InvalidGoto { step, state, var } => write!(f,
+                "no goto on `{}` from state {} at position {}; the table is inconsistent", names.variable(var), state, step),
+            Self::UnexpectedWord { step, expected, word } => write!(f,
+                "expected {}, found {} at position {}", names.token(expected), names.token(word), step),
+            Self::NoPrediction { step, var, word } => write!(f,
+                "no production of `{}` starts with {}, found at position {}", names.variable(var), names.token(word), step),
+            Self::UnknownWord { step, word } => write!(f,
+                "unknown word {} at position {}, which is not a terminal of the grammar", word, step),
+            Self::InvalidEdit { ref edit, len } => write!(f,
+                "edit {}..{} is out of bounds for {} words", edit.start, edit.end, len),
+        }
+    }
+}
+
+impl<E> fmt::Display for ParseError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(&Names::default(), f)
+    }
+}
+
+/// The source of an `InputError` is the error of the input.
+impl<E: Error + 'static> Error for ParseError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InputError(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 impl<'a, P, I, T, F> Parse<'a, P, I, T, F>
@@ -31,18 +107,50 @@ where
     P: LR1Table,
     F: Fn(&T) -> usize,
 {
+    /// Parses `input` starting from entry point `entry`; see
+    /// [`Grammar::entry_points`](crate::grammar::Grammar::entry_points).
+    ///
+    /// # Panics
+    /// Panics if `entry` is not an entry point of the grammar.
     #[must_use]
-    pub fn new(table: &'a P, input: I, get_id: F) -> Self {
+    pub fn new(table: &'a P, entry: usize, input: I, get_id: F) -> Self {
         Self {
             table,
             input,
             get_id,
             step:          0, // only really useful for debugging, not strictly necessary
             next_word:     None,
-            next_action:   Action::Shift(P::START_STATE),
+            next_action:   Action::Shift(table.start_state(entry)),
             state_history: Vec::new(),
+            trace:         None,
         }
     }
+
+    /// Records every step of the parse, see [`Parse::trace`].
+    #[must_use]
+    pub fn traced(mut self) -> Self {
+        self.trace = Some(Trace::default());
+        self
+    }
+
+    /// The steps so far, if the parse is [`traced`](Parse::traced).
+    #[must_use]
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// The next word, which the parse has read but not shifted yet.
+    #[must_use]
+    pub fn lookahead(&self) -> Option<&T> {
+        self.next_word.as_ref()
+    }
+
+    /// The stack of states, the current one last. Empty until the first
+    /// event.
+    #[must_use]
+    pub fn states(&self) -> &[usize] {
+        &self.state_history
+    }
 }
 
 impl<'a, P, I, T, E, F> Iterator for Parse<'a, P, I, T, F>
@@ -56,16 +164,20 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         match self.next_action {
             Action::Invalid => {
-                Some(Err(ParseError::InvalidAction {
-                    step: self.step,
-                    state: *self.state_history.last().unwrap(),
-                    word: self.next_word.as_ref().map(&self.get_id),
-                }))
+                self.record(Action::Invalid, None);
+                let state = *self.state_history.last().unwrap();
+                let word = self.next_word.as_ref().map(&self.get_id);
+                Some(Err(rejection(self.table, self.step, state, word)))
             },
             Action::Accept => {
+                self.record(Action::Accept, None);
                 None
             },
             Action::Shift(state) => {
+                // The start state is pushed without a word, and not recorded.
+                if !self.state_history.is_empty() {
+                    self.record(Action::Shift(state), None);
+                }
                 let curr_word = mem::replace(&mut self.next_word, match self.input.next().transpose() {
                     Ok(val) => val,
                     Err(err) => return Some(Err(ParseError::InputError(err))),
@@ -85,15 +197,17 @@ where
                 // lookup which variable and how many frontier elements are consumed
                 let reduction = self.table.reduction(production);
 
+                // state is rewinded to before words associated with reduction
+                let old_state = self.state_history[self.state_history.len() - reduction.count - 1];
+                let goto = self.table.goto(old_state, reduction.var);
+                self.record(Action::Reduce(production), goto);
+
                 // consume part of frontier
                 for _ in 0..reduction.count {
                     self.state_history.pop();
                 }
 
-                // state is rewinded to before words associated with reduction
-                let old_state = *self.state_history.last().unwrap();
-
-                if let Some(state) = self.table.goto(old_state, reduction.var) {
+                if let Some(state) = goto {
                     self.next_action = self.table.action(state, self.next_word.as_ref().map(&self.get_id));
                     self.state_history.push(state);
                     Some(Ok(Event::Reduce {
@@ -111,4 +225,29 @@ where
             },
         }
     }
-}
\ No newline at end of file
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+impl<'a, P, I, T, F> Parse<'a, P, I, T, F>
+where
+    F: Fn(&T) -> usize,
+{
+    fn record(&mut self, action: Action, goto: Option<usize>) {
+        if let Some(trace) = &mut self.trace {
+            let lookahead = self.next_word.as_ref().map(&self.get_id);
+            trace.push(&self.state_history, lookahead, action, goto);
+        }
+    }
+}
+
+/// The error for `state` rejecting `word`, which is unknown if outside the
+/// alphabet of `table`.
+pub(super) fn rejection<P: LR1Table, E>(table: &P, step: usize, state: usize, word: Option<usize>) -> ParseError<E> {
+    match word {
+        Some(word) if word >= table.word_count() => ParseError::UnknownWord { step, word },
+        _ => ParseError::InvalidAction { step, state, word },
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use super::{Action, Completion, Conflict, ConstructionError, NaiveLR1Table, Parse, ParseError};
+use crate::automata::{LR0A, SLR1A, LALR1A, LR1A};
+use crate::grammar::Grammar;
+
+/// The kind of automaton a [`Parser`] constructs its table from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutomatonKind {
+    /// See [`LR0A`].
+    LR0,
+    /// See [`SLR1A`].
+    SLR1,
+    /// See [`LALR1A`].
+    LALR1,
+    /// See [`LR1A`].
+    LR1,
+}
+
+/// A grammar bundled with the parse table constructed for it. Unlike the
+/// automata, which may borrow their grammar, a parser owns everything it
+/// needs, so it can be stored in long-lived structs and shared between
+/// threads.
+#[derive(Debug)]
+pub struct Parser {
+    grammar: Arc<Grammar>,
+    kind: AutomatonKind,
+    table: NaiveLR1Table,
+}
+
+impl Parser {
+    /// Constructs a parser, failing on the first conflict encountered.
+    ///
+    /// # Errors
+    /// Returns the first conflict encountered during table construction.
+    pub fn new<G: Into<Arc<Grammar>>>(grammar: G, kind: AutomatonKind) -> Result<Self, ConstructionError> {
+        Self::with_conflict_resolution(grammar, kind, Err)
+    }
+
+    /// Constructs a parser, resolving conflicts as in [`NaiveLR1Table::build`].
+    ///
+    /// # Errors
+    /// Returns the first conflict that `conflict_resolution` fails to resolve.
+    pub fn with_conflict_resolution<G, F>(grammar: G, kind: AutomatonKind, conflict_resolution: F) -> Result<Self, ConstructionError>
+    where
+        G: Into<Arc<Grammar>>,
+        F: FnMut(Conflict) -> Result<Action, Conflict>,
+    {
+        let grammar = grammar.into();
+        let table = match kind {
+            AutomatonKind::LR0 => NaiveLR1Table::build(&LR0A::new(&*grammar), conflict_resolution)?,
+            AutomatonKind::SLR1 => NaiveLR1Table::build(&SLR1A::new(&*grammar), conflict_resolution)?,
+            AutomatonKind::LALR1 => NaiveLR1Table::build(&LALR1A::new(&*grammar), conflict_resolution)?,
+            AutomatonKind::LR1 => NaiveLR1Table::build(&LR1A::new(&*grammar), conflict_resolution)?,
+        };
+        Ok(Self { grammar, kind, table })
+    }
+
+    /// The grammar the parser was constructed from.
+    #[must_use]
+    pub fn grammar(&self) -> &Arc<Grammar> {
+        &self.grammar
+    }
+
+    /// The kind of automaton the table was constructed from.
+    #[must_use]
+    pub fn kind(&self) -> AutomatonKind {
+        self.kind
+    }
+
+    /// The parse table.
+    #[must_use]
+    pub fn table(&self) -> &NaiveLR1Table {
+        &self.table
+    }
+
+    /// Parses `input` from entry point `entry` with the table; see [`Parse`].
+    pub fn parse<I, T, F>(&self, entry: usize, input: I, get_id: F) -> Parse<'_, NaiveLR1Table, I, T, F>
+    where
+        F: Fn(&T) -> usize,
+    {
+        Parse::new(&self.table, entry, input, get_id)
+    }
+
+    /// The words, `None` for EOF, that may follow `prefix` when parsing from
+    /// entry point `entry`; see [`Completion`].
+    ///
+    /// # Errors
+    /// Returns the first error, if the prefix cannot start a valid input.
+    pub fn complete<I, T, E, F>(&self, entry: usize, prefix: I, get_id: F) -> Result<Vec<Option<usize>>, ParseError<E>>
+    where
+        I: IntoIterator<Item=Result<T, E>>,
+        F: Fn(&T) -> usize,
+    {
+        Ok(Completion::new(&self.table, entry, prefix, get_id)?.words(&self.table, &self.grammar))
+    }
+}
@@ -0,0 +1,165 @@
+use std::error::Error;
+use std::fmt;
+use super::{Event, LR1Table, Parse, ParseError};
+use crate::grammar::{Describe, Names};
+
+/// A range of source positions, such as byte offsets, from `start`
+/// inclusive to `end` exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The first position covered.
+    pub start: usize,
+    /// The position after the last one covered.
+    pub end: usize,
+}
+
+/// Something located in the source, typically a token.
+pub trait Spanned {
+    /// Where in the source it is.
+    fn span(&self) -> Span;
+}
+
+/// An error of a [`SpannedParse`], with where it occurred.
+#[derive(Debug)]
+pub struct SpannedError<E> {
+    /// The error.
+    pub error: ParseError<E>,
+    /// The span of the offending token. At EOF, or when the input itself
+    /// fails, this is the empty span after the last token read.
+    pub span: Span,
+}
+
+/// A [`Parse`] of [`Spanned`] tokens, with the span of every event: a shift
+/// spans its token, and a reduction spans its children, or is empty after
+/// the preceding token if it has none. Returned by [`Parse::spanned`].
+pub struct SpannedParse<'a, P, I, T, F> {
+    parse: Parse<'a, P, I, T, F>,
+    spans: Vec<Span>, // one per symbol of the parse stack
+    end:   usize,     // end of the last token shifted
+}
+
+impl Span {
+    /// The span from `start` to `end`.
+    #[must_use]
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The empty span at `pos`.
+    #[must_use]
+    pub fn empty(pos: usize) -> Self {
+        Self { start: pos, end: pos }
+    }
+
+    /// Whether the span covers nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// The span from the start of `self` to the end of `other`, which
+    /// follows it.
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        Self { start: self.start, end: other.end }
+    }
+}
+
+impl Spanned for Span {
+    fn span(&self) -> Span {
+        *self
+    }
+}
+
+impl<T: Spanned> Spanned for &T {
+    fn span(&self) -> Span {
+        (*self).span()
+    }
+}
+
+impl<T> Spanned for (T, Span) {
+    fn span(&self) -> Span {
+        self.1
+    }
+}
+
+impl<E> Describe for SpannedError<E> {
+    fn describe(&self, names: &Names, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.error.named(names), self.span.start, self.span.end)
+    }
+}
+
+impl<E> fmt::Display for SpannedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(&Names::default(), f)
+    }
+}
+
+impl<E: Error + 'static> Error for SpannedError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl<'a, P, I, T, F> Parse<'a, P, I, T, F>
+where
+    P: LR1Table,
+    T: Spanned,
+    F: Fn(&T) -> usize,
+{
+    /// Reports the span of every event and error.
+    #[must_use]
+    pub fn spanned(self) -> SpannedParse<'a, P, I, T, F> {
+        SpannedParse { parse: self, spans: Vec::new(), end: 0 }
+    }
+}
+
+impl<'a, P, I, T, F> SpannedParse<'a, P, I, T, F> {
+    /// The underlying parse, e.g. for its [`trace`](Parse::trace).
+    #[must_use]
+    pub fn parse(&self) -> &Parse<'a, P, I, T, F> {
+        &self.parse
+    }
+}
+
+impl<'a, P, I, T, E, F> Iterator for SpannedParse<'a, P, I, T, F>
+where
+    P: LR1Table,
+    I: Iterator<Item=Result<T, E>>,
+    T: Spanned,
+    F: Fn(&T) -> usize,
+{
+    type Item = Result<(Event<T>, Span), SpannedError<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parse.next()? {
+            Ok(Event::Shift(word)) => {
+                let span = word.span();
+                self.end = span.end;
+                self.spans.push(span);
+                Some(Ok((Event::Shift(word), span)))
+            },
+            Ok(Event::Reduce { var, child_count, production }) => {
+                let first = self.spans.len() - child_count;
+                let span = match self.spans.last() {
+                    Some(&last) if child_count > 0 => self.spans[first].merge(last),
+                    _ => Span::empty(self.end),
+                };
+                self.spans.truncate(first);
+                self.spans.push(span);
+                Some(Ok((Event::Reduce { var, child_count, production }, span)))
+            },
+            Err(error) => {
+                let lookahead = self.parse.lookahead().map(Spanned::span);
+                let span = match (&error, lookahead) {
+                    // The input failed while reading the token after the
+                    // lookahead.
+                    (ParseError::InputError(_), Some(lookahead)) => Span::empty(lookahead.end),
+                    (ParseError::InputError(_), None) => Span::empty(self.end),
+                    (_, lookahead) => lookahead.unwrap_or_else(|| Span::empty(self.end)),
+                };
+                Some(Err(SpannedError { error, span }))
+            },
+        }
+    }
+}
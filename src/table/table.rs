@@ -3,8 +3,9 @@
 use super::{Conflict, ConstructionError};
 use crate::grammar::Symbol;
 use crate::automata::{LR0Item, LRAutomaton, DottedItem};
+use crate::parallel::{self, MaybeSync};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
     Invalid,
     Accept,
@@ -21,7 +22,14 @@ pub struct Reduction {
 }
 
 pub trait LR1Table {
-    const START_STATE: usize = 0;
+    /// The state parsing from entry point `entry` starts in.
+    ///
+    /// # Panics
+    /// Panics if `entry` is not an entry point of the grammar.
+    fn start_state(&self, entry: usize) -> usize;
+    /// The number of terminals. The action on any other word is
+    /// [`Invalid`](Action::Invalid).
+    fn word_count(&self) -> usize;
     fn action(&self, state: usize, word: Option<usize>) -> Action;
     fn goto(&self, state: usize, var: usize) -> Option<usize>;
     fn reduction(&self, production: usize) -> Reduction;
@@ -32,6 +40,7 @@ pub struct NaiveLR1Table {
     actions:    Vec<Action>,        // lookup what action to perform given state and word
     gotos:      Vec<Option<usize>>, // lookup what state should be transitioned to after reduction
     reductions: Vec<Reduction>,     // production --> rule and number of symbols
+    start_states: Vec<usize>,       // entry point --> state
     word_count: usize,
     var_count:  usize,
 }
@@ -40,14 +49,14 @@ impl NaiveLR1Table {
     /// # Errors
     pub fn build<'a, T, F>(automaton: &'a T, mut conflict_resolution: F) -> Result<NaiveLR1Table, ConstructionError>
     where
-        T: LRAutomaton<'a>,
+        T: LRAutomaton<'a> + MaybeSync,
         <T::ItemSet as IntoIterator>::Item: DottedItem,
         F: FnMut(Conflict) -> Result<Action, Conflict>,
     {
         let grammar = automaton.grammar();
 
         let word_count = grammar.word_count() + 1; // +1 for eof
-        let var_count = grammar.rules().len() - 1; // implicit start variable not needed in goto table
+        let var_count = grammar.rules().len() - grammar.entry_points().len(); // implicit start variables not needed in goto table
         let num_states = automaton.state_count();
 
         let mut table = NaiveLR1Table {
@@ -56,52 +65,49 @@ impl NaiveLR1Table {
             reductions: grammar.productions().into_iter()
                 .map(|(A, beta)| Reduction { var: A, count: beta.len() })
                 .collect(),
+            start_states: (0..grammar.entry_points().len()).map(|entry| automaton.start_state(entry)).collect(),
             word_count,
             var_count,
         };
 
-        for i in 0..num_states {
-            for item in automaton.items(i) {
-                if !item.is_complete() {
-                    let symbol = item.symbol_at_dot().unwrap();
-                    if let Symbol::Terminal(word) = symbol {
-                        // CASE 1: item is incomplete and has a terminal symbol at dot.
+        // The candidate actions of each state are independent of one another,
+        // so they are collected first (in parallel, if enabled). Conflicts are
+        // then resolved sequentially, in state order.
+        let reductions = &table.reductions;
+        let rows = parallel::map_range(num_states, |i| candidate_actions(automaton, i, reductions, var_count));
+
+        for (i, row) in rows.into_iter().enumerate() {
+            for (column, candidate) in row {
+                let action = table.actions.get_mut(i * word_count + column).unwrap();
 
-                        let action = table.actions.get_mut(i * word_count + word + 1).unwrap();
-                        let next_state = automaton.transition(i, symbol).unwrap();
-    
+                match candidate {
+                    Action::Shift(next_state) => {
                         // Note: shift-shift conflicts cannot occur
                         if let Action::Reduce(production) = *action {
-                            *action = conflict_resolution(Conflict::ShiftReduce { word, next_state, production })
+                            *action = conflict_resolution(Conflict::ShiftReduce { word: column - 1, next_state, production })
                                 .map_err(|conflict| ConstructionError { state: i, conflict })?;
                         } else {
-                            *action = Action::Shift(next_state);
+                            *action = candidate;
                         }
-                    }
-                } else if table.reductions[item.production()].var < var_count {
-                    // CASE 2: item is complete and does not have the start symbol on LHS.
-
-                    for lookahead in item.lookaheads() {
-                        let column = lookahead.map_or(0, |a| a + 1);
-                        let action = table.actions.get_mut(i * word_count + column).unwrap();
-                        
+                    },
+                    Action::Reduce(production2) => {
                         match *action {
                             Action::Shift(state) => {
-                                *action = conflict_resolution(Conflict::ShiftReduce { word: column - 1, next_state: state, production: item.production() })
+                                *action = conflict_resolution(Conflict::ShiftReduce { word: column - 1, next_state: state, production: production2 })
                                     .map_err(|conflict| ConstructionError { state: i, conflict })?;
                             }
                             Action::Reduce(production1) => {
-                                *action = conflict_resolution(Conflict::ReduceReduce { production1, production2: item.production() })
+                                *action = conflict_resolution(Conflict::ReduceReduce { production1, production2 })
                                     .map_err(|conflict| ConstructionError { state: i, conflict })?;
                             }
                             _ => {
-                                *action = Action::Reduce(item.production());
+                                *action = candidate;
                             }
                         }
-                    }
-                } else {
-                    // CASE 3: item is complete and has start symbol on LHS (lookahead will always be {eof}).
-                    table.actions[i * word_count] = Action::Accept;
+                    },
+                    _ => {
+                        *action = candidate;
+                    },
                 }
             }
 
@@ -112,11 +118,28 @@ impl NaiveLR1Table {
 
         Ok(table)
     }
+
+    /// The number of states, i.e. rows of the table.
+    #[must_use]
+    pub fn state_count(&self) -> usize {
+        self.actions.len() / self.word_count
+    }
 }
 
 impl LR1Table for NaiveLR1Table {
+    fn start_state(&self, entry: usize) -> usize {
+        *self.start_states.get(entry).unwrap_or_else(|| panic!("invalid entry point {}", entry))
+    }
+
+    fn word_count(&self) -> usize {
+        self.word_count - 1
+    }
+
     fn action(&self, state: usize, word: Option<usize>) -> Action {
-        self.actions[state * self.word_count + word.map_or(0, |a| a + 1)]
+        match word {
+            Some(a) if a >= self.word_count - 1 => Action::Invalid,
+            _ => self.actions[state * self.word_count + word.map_or(0, |a| a + 1)],
+        }
     }
 
     fn goto(&self, state: usize, var: usize) -> Option<usize> {
@@ -126,4 +149,39 @@ impl LR1Table for NaiveLR1Table {
     fn reduction(&self, production: usize) -> Reduction {
         self.reductions[production]
     }
+}
+
+// =================
+// === INTERNALS ===
+// =================
+
+/// Lists the (column, action) pairs contributed by each item of a state, in
+/// item order, without resolving conflicts.
+fn candidate_actions<'a, T>(automaton: &'a T, state: usize, reductions: &[Reduction], var_count: usize) -> Vec<(usize, Action)>
+where
+    T: LRAutomaton<'a>,
+    <T::ItemSet as IntoIterator>::Item: DottedItem,
+{
+    let mut row = Vec::new();
+
+    for item in automaton.items(state) {
+        if !item.is_complete() {
+            let symbol = item.symbol_at_dot().unwrap();
+            if let Symbol::Terminal(word) = symbol {
+                // CASE 1: item is incomplete and has a terminal symbol at dot.
+                let next_state = automaton.transition(state, symbol).unwrap();
+                row.push((word + 1, Action::Shift(next_state)));
+            }
+        } else if reductions[item.production()].var < var_count {
+            // CASE 2: item is complete and does not have a start symbol on LHS.
+            for lookahead in item.lookaheads() {
+                row.push((lookahead.map_or(0, |a| a + 1), Action::Reduce(item.production())));
+            }
+        } else {
+            // CASE 3: item is complete and has a start symbol on LHS (lookahead will always be {eof}).
+            row.push((0, Action::Accept));
+        }
+    }
+
+    row
 }
\ No newline at end of file
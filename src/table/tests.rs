@@ -1,7 +1,9 @@
 use std::iter::once;
-use super::{Event, Parse, Conflict, NaiveLR1Table};
-use crate::grammar::{GrammarBuilder, Symbol::Terminal as Word, Symbol::Variable as Var};
-use crate::automata::LALR1A;
+use std::sync::Arc;
+use super::{Action, Completion, Event, LR1Table, Parse, Span, SyntaxNode, SyntaxTree, TraceStep, Conflict, NaiveLR1Table, AutomatonKind, Parser, LL1Conflict, LL1Parse, LL1Table, ParseError};
+use crate::grammar::{Grammar, GrammarBuilder, EbnfBuilder, Expr, Symbol::Terminal as Word, Symbol::Variable as Var};
+use crate::grammar::generate::{self, Sampler, XorShift};
+use crate::automata::{LALR1A, LR1A};
 
 #[test]
 fn parentheses_grammar() {
@@ -36,7 +38,7 @@ fn parentheses_grammar() {
     for sentence in all_sentences(2, 12) {
         let input = sentence.iter().copied().map(Ok::<_,()>);
         let valid = is_valid(&sentence);
-        let parse = Parse::new(&parser, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>();
+        let parse = Parse::new(&parser, 0, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>();
 
         assert!(parse.is_ok() == valid, 
             "Input {:?} is {}",
@@ -58,7 +60,7 @@ fn parentheses_grammar_2() {
 
     let input = vec![0, 0, 1, 1].into_iter().map(Ok::<_,()>);
 
-    let nodes = Parse::new(&parser, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>().unwrap();
+    let nodes = Parse::new(&parser, 0, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>().unwrap();
 
     assert_eq!(nodes[0], Event::Shift(0));
     assert_eq!(nodes[1], Event::Shift(0));
@@ -70,6 +72,705 @@ fn parentheses_grammar_2() {
     assert_eq!(nodes[7], Event::Reduce { var: 0, child_count: 1, production: 1 });
 }
 
+#[test]
+fn parentheses_grammar_lr1() {
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Word(0), Var(0), Word(1)])
+                                                  .add_production([Word(0), Word(1)])
+                                       .build().unwrap();
+
+    let lalr1_parser = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    let lr1_parser = NaiveLR1Table::build(&LR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+
+    for sentence in all_sentences(2, 10) {
+        let lalr1_parse = Parse::new(&lalr1_parser, 0, sentence.iter().copied().map(Ok::<_,()>), |a: &usize| *a)
+            .collect::<Result<Vec<_>, _>>();
+        let lr1_parse = Parse::new(&lr1_parser, 0, sentence.iter().copied().map(Ok::<_,()>), |a: &usize| *a)
+            .collect::<Result<Vec<_>, _>>();
+
+        match (lalr1_parse, lr1_parse) {
+            (Ok(lalr1_events), Ok(lr1_events)) => assert_eq!(lalr1_events, lr1_events),
+            (Err(_), Err(_)) => {},
+            _ => panic!("LALR(1) and LR(1) tables disagree on input {:?}", sentence),
+        }
+    }
+}
+
+#[test]
+fn lr1_but_not_lalr1_grammar() {
+    // S : a A d | b B d | a B e | b A e,
+    // A : c,
+    // B : c,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Word(0), Var(1), Word(3)])
+                                                  .add_production([Word(1), Var(2), Word(3)])
+                                                  .add_production([Word(0), Var(2), Word(4)])
+                                                  .add_production([Word(1), Var(1), Word(4)])
+                                       .new_rule().add_production([Word(2)])
+                                       .new_rule().add_production([Word(2)])
+                                       .build().unwrap();
+
+    assert!(NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).is_err());
+    let parser = NaiveLR1Table::build(&LR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+
+    let valid = [[0, 2, 3], [1, 2, 3], [0, 2, 4], [1, 2, 4]];
+    for sentence in all_sentences(5, 3) {
+        let input = sentence.iter().copied().map(Ok::<_,()>);
+        let parse = Parse::new(&parser, 0, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>();
+        assert_eq!(parse.is_ok(), valid.iter().any(|v| v == sentence.as_slice()), "Input {:?}", sentence);
+    }
+
+    let input = vec![0, 2, 4].into_iter().map(Ok::<_,()>);
+    let nodes = Parse::new(&parser, 0, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(nodes[2], Event::Reduce { var: 2, child_count: 1, production: 5 });
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_construction_is_deterministic() {
+    // E : E + T | T,
+    // T : T * F | F,
+    // F : ( E ) | n,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Var(1), Word(1), Var(2)])
+                                                  .add_production([Var(2)])
+                                       .new_rule().add_production([Word(2), Var(0), Word(3)])
+                                                  .add_production([Word(4)])
+                                       .build().unwrap();
+
+    let build_tables = |num_threads: usize| {
+        rayon::ThreadPoolBuilder::new().num_threads(num_threads).build().unwrap().install(|| {
+            let lalr1_table = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+            let lr1_table = NaiveLR1Table::build(&LR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+            format!("{:?}\n{:?}", lalr1_table, lr1_table)
+        })
+    };
+
+    let expected = build_tables(1);
+    for num_threads in 2..=4 {
+        assert_eq!(build_tables(num_threads), expected);
+    }
+}
+
+#[test]
+fn owned_parser_across_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Parser>();
+    assert_send_sync::<LALR1A<Grammar>>();
+    assert_send_sync::<LR1A<Arc<Grammar>>>();
+
+    let grammar = Arc::new(GrammarBuilder::new().new_rule().add_production([Var(0), Var(1)])
+                                                           .add_production([Var(1)])
+                                                .new_rule().add_production([Word(0), Var(0), Word(1)])
+                                                           .add_production([Word(0), Word(1)])
+                                                .build().unwrap());
+
+    let lr1a = LR1A::new(grammar.clone());
+    let parser = Arc::new(Parser::new(grammar, AutomatonKind::LALR1).unwrap());
+    let expected = NaiveLR1Table::build(&lr1a, |conflict: Conflict| { Err(conflict) }).unwrap();
+
+    let handle = std::thread::spawn(move || {
+        let input = vec![0, 0, 1, 1].into_iter().map(Ok::<_,()>);
+        parser.parse(0, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>().unwrap()
+    });
+
+    let input = vec![0, 0, 1, 1].into_iter().map(Ok::<_,()>);
+    let events = Parse::new(&expected, 0, input, |a: &usize| *a).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(handle.join().unwrap(), events);
+}
+
+#[test]
+fn ebnf_synthetic_nodes_flatten() {
+    #[derive(Debug, PartialEq)]
+    enum Tree {
+        Leaf(usize),
+        Node(usize, Vec<Tree>),
+    }
+
+    // List : [ sep_by(Item, ,) ],
+    // Item : n | ( n* ),
+    let ebnf = EbnfBuilder::new().new_rule().add_production([Word(0).into(), Expr::sep_by(Var(1), Word(2)), Word(1).into()])
+                                 .new_rule().add_production([Word(3)])
+                                            .add_production([Word(4).into(), Expr::star(Word(3)), Word(5).into()])
+                                 .build().unwrap();
+    let parser = NaiveLR1Table::build(&LALR1A::new(ebnf.grammar()), |conflict: Conflict| { Err(conflict) }).unwrap();
+
+    // [ n , ( n n ) , ( ) ]
+    let input = vec![0, 3, 2, 4, 3, 3, 5, 2, 4, 5, 1].into_iter().map(Ok::<_,()>);
+    let mut stack = Vec::new();
+    for event in Parse::new(&parser, 0, input, |a: &usize| *a) {
+        match event.unwrap() {
+            Event::Shift(word) => stack.push(Tree::Leaf(word)),
+            Event::Reduce { var, child_count, .. } => {
+                // Children of synthetic nodes are spliced into their parent.
+                let mut children = Vec::new();
+                for child in stack.drain(stack.len() - child_count..) {
+                    match child {
+                        Tree::Node(child_var, grandchildren) if ebnf.origin(child_var).is_some() => {
+                            children.extend(grandchildren);
+                        },
+                        child => children.push(child),
+                    }
+                }
+                stack.push(Tree::Node(var, children));
+            },
+        }
+    }
+
+    use Tree::{Leaf, Node};
+    assert_eq!(stack, [
+        Node(0, vec![
+            Leaf(0),
+            Node(1, vec![Leaf(3)]),
+            Leaf(2),
+            Node(1, vec![Leaf(4), Leaf(3), Leaf(3), Leaf(5)]),
+            Leaf(2),
+            Node(1, vec![Leaf(4), Leaf(5)]),
+            Leaf(1),
+        ]),
+    ]);
+}
+
+#[test]
+fn multiple_entry_points() {
+    // Expr : Expr + n | n,
+    // Stmt : Expr ;,
+    // File : File Stmt | Stmt,
+    let (file, stmt, expr) = (0, 1, 2);
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Word(1)])
+                                                  .add_production([Word(1)])
+                                       .new_rule().add_production([Var(0), Word(2)])
+                                       .new_rule().add_production([Var(2), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .entry_points([2, 1, 0])
+                                       .build().unwrap();
+    assert_eq!(grammar.entry_points(), [2, 1, 0]);
+    assert_eq!((grammar.start_var(expr), grammar.start_production(expr)), (5, 7));
+
+    let lalr1_table = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    let lr1_table = NaiveLR1Table::build(&LR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+
+    // n + n
+    let sentence = [1, 0, 1];
+    for table in [&lalr1_table, &lr1_table] {
+        let parse = |entry: usize, sentence: &[usize]| {
+            Parse::new(table, entry, sentence.iter().copied().map(Ok::<_,()>), |a: &usize| *a)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        assert_eq!(parse(expr, &sentence).unwrap(), [
+            Event::Shift(1),
+            Event::Reduce { var: 0, child_count: 1, production: 1 },
+            Event::Shift(0),
+            Event::Shift(1),
+            Event::Reduce { var: 0, child_count: 3, production: 0 },
+        ]);
+        assert!(parse(stmt, &sentence).is_err());
+        assert!(parse(file, &sentence).is_err());
+
+        // n + n ; n ;
+        let sentence = [1, 0, 1, 2, 1, 2];
+        assert!(parse(expr, &sentence).is_err());
+        assert!(parse(stmt, &sentence).is_err());
+        assert!(parse(stmt, &sentence[..4]).is_ok());
+        let events = parse(file, &sentence).unwrap();
+        assert_eq!(events.last(), Some(&Event::Reduce { var: 2, child_count: 2, production: 3 }));
+    }
+}
+
+#[test]
+fn ll1_agrees_with_lalr1() {
+    // E : T E',
+    // E' : + T E' | ,
+    // T : ( E ) | n,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(2), Var(1)])
+                                       .new_rule().add_production([Word(0), Var(2), Var(1)])
+                                                  .add_production([])
+                                       .new_rule().add_production([Word(1), Var(0), Word(2)])
+                                                  .add_production([Word(3)])
+                                       .build().unwrap();
+
+    let ll1_table = LL1Table::build(&grammar, Err).unwrap();
+    let lalr1_table = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    assert_eq!(ll1_table.predict(1, Some(0)), Some(1));
+    assert_eq!(ll1_table.predict(1, Some(2)), Some(2));
+    assert_eq!(ll1_table.predict(1, None), Some(2));
+    assert_eq!(ll1_table.predict(2, Some(0)), None);
+
+    for sentence in all_sentences(4, 7) {
+        let ll1_parse = LL1Parse::new(&ll1_table, 0, sentence.iter().copied().map(Ok::<_,()>), |a: &usize| *a)
+            .collect::<Result<Vec<_>, _>>();
+        let lalr1_parse = Parse::new(&lalr1_table, 0, sentence.iter().copied().map(Ok::<_,()>), |a: &usize| *a)
+            .collect::<Result<Vec<_>, _>>();
+
+        match (ll1_parse, lalr1_parse) {
+            (Ok(ll1_events), Ok(lalr1_events)) => assert_eq!(ll1_events, lalr1_events),
+            (Err(_), Err(_)) => {},
+            _ => panic!("LL(1) and LALR(1) tables disagree on input {:?}", sentence),
+        }
+    }
+
+    let parse = LL1Parse::new(&ll1_table, 0, vec![3, 3].into_iter().map(Ok::<_,()>), |a: &usize| *a)
+        .collect::<Result<Vec<_>, _>>();
+    assert!(matches!(parse, Err(ParseError::NoPrediction { step: 1, var: 1, word: Some(3) })));
+    let parse = LL1Parse::new(&ll1_table, 0, vec![1, 3].into_iter().map(Ok::<_,()>), |a: &usize| *a)
+        .collect::<Result<Vec<_>, _>>();
+    assert!(matches!(parse, Err(ParseError::UnexpectedWord { step: 2, expected: Some(2), word: None })));
+}
+
+#[test]
+fn ll1_conflicts() {
+    // E : E + n | n,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Word(1)])
+                                                  .add_production([Word(1)])
+                                       .build().unwrap();
+    assert_eq!(
+        LL1Table::build(&grammar, Err).unwrap_err(),
+        LL1Conflict::FirstFirst { var: 0, word: 1, production1: 0, production2: 1 },
+    );
+
+    // S : i S E | a,
+    // E : e S | ,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Word(0), Var(0), Var(1)])
+                                                  .add_production([Word(1)])
+                                       .new_rule().add_production([Word(2), Var(0)])
+                                                  .add_production([])
+                                       .build().unwrap();
+    let mut conflicts = Vec::new();
+    let table = LL1Table::build(&grammar, |conflict| {
+        conflicts.push(conflict);
+        Ok(2) // bind `else` to the nearest `if`
+    }).unwrap();
+    assert_eq!(conflicts, [LL1Conflict::FirstFollow { var: 1, word: Some(2), production1: 2, production2: 3 }]);
+    assert_eq!(
+        LL1Table::build(&grammar, |_| Ok(0)).unwrap_err(),
+        LL1Conflict::FirstFollow { var: 1, word: Some(2), production1: 2, production2: 3 },
+    );
+
+    // i i a e a
+    let events = LL1Parse::new(&table, 0, vec![0, 0, 1, 2, 1].into_iter().map(Ok::<_,()>), |a: &usize| *a)
+        .collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(events.iter().filter(|event| matches!(event, Event::Reduce { production: 2, .. })).count(), 1);
+    assert_eq!(events[events.len() - 2], Event::Reduce { var: 1, child_count: 0, production: 3 });
+}
+
+#[test]
+fn generated_sentences_and_near_misses() {
+    use crate::grammar::generate::{coverage, near_misses, Sampler};
+
+    // Expr : Expr + Term | Term,
+    // Term : Term * n | n | ( Expr ),
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Var(1), Word(1), Word(2)])
+                                                  .add_production([Word(2)])
+                                                  .add_production([Word(3), Var(0), Word(4)])
+                                       .build().unwrap();
+    let table = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    let parses = |sentence: &[usize]| {
+        Parse::new(&table, 0, sentence.iter().copied().map(Ok::<_,()>), |a: &usize| *a)
+            .collect::<Result<Vec<_>, _>>()
+            .is_ok()
+    };
+
+    let mut sampler = Sampler::new(&grammar, 7).max_depth(10);
+    let sentences = coverage(&grammar, 0).into_iter()
+        .chain((0..50).map(|_| sampler.sample(0).unwrap()))
+        .map(|derivation| derivation.sentence().to_vec());
+    for sentence in sentences {
+        assert!(parses(&sentence), "Input {:?}", sentence);
+        for miss in near_misses(&grammar, 0, &sentence) {
+            assert!(!parses(&miss), "Input {:?}", miss);
+        }
+    }
+}
+
+#[test]
+fn differential_lalr1_lr1() {
+    use crate::testing::Differential;
+
+    // Expr : Expr + Term | Term,
+    // Term : Term * n | n | ( Expr ),
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Var(1), Word(1), Word(2)])
+                                                  .add_production([Word(2)])
+                                                  .add_production([Word(3), Var(0), Word(4)])
+                                       .entry_points([0, 1])
+                                       .build().unwrap();
+    let checked = Differential::new(&grammar).unwrap().max_len(5).run().unwrap_or_else(|divergence| panic!("{}", divergence));
+    assert!(checked > 1000);
+}
+
+#[test]
+fn differential_reports_divergence() {
+    use crate::testing::Differential;
+    use super::Action;
+
+    // E : E + E | n,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Var(0)])
+                                                  .add_production([Word(1)])
+                                       .build().unwrap();
+    let automaton = LALR1A::new(&grammar);
+    let shift = NaiveLR1Table::build(&automaton, |conflict| match conflict {
+        Conflict::ShiftReduce { next_state, .. } => Ok(Action::Shift(next_state)),
+        conflict => Err(conflict),
+    }).unwrap();
+    let reduce = NaiveLR1Table::build(&automaton, |conflict| match conflict {
+        Conflict::ShiftReduce { production, .. } => Ok(Action::Reduce(production)),
+        conflict => Err(conflict),
+    }).unwrap();
+
+    let differential = Differential::empty(&grammar).table("shift", shift).table("reduce", reduce).max_len(5).samples(0);
+    assert!(differential.check(0, &[1, 0, 1]).is_ok());
+    assert!(differential.check(0, &[1, 0, 1, 0]).is_ok());
+    let divergence = differential.run().unwrap_err();
+    assert_eq!(divergence.sentence(), [1, 0, 1, 0, 1]);
+    assert_eq!(divergence.position(), 5);
+    assert!(divergence.runs().iter().all(|(_, run)| run.accepted()));
+    assert_eq!(divergence.runs()[0].1.events()[5].0, Event::Shift(0));
+    assert_eq!(divergence.runs()[1].1.events()[5].0, Event::Reduce { var: 0, child_count: 3, production: 0 });
+}
+
+#[test]
+fn lr0_and_slr1_tables() {
+    use crate::testing::Differential;
+    use crate::automata::{LR0A, SLR1A};
+
+    // E : T + E | T,
+    // T : n,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(1), Word(0), Var(0)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Word(1)])
+                                       .build().unwrap();
+    assert!(NaiveLR1Table::build(&LR0A::new(&grammar), |conflict: Conflict| { Err(conflict) }).is_err());
+    let slr1_table = NaiveLR1Table::build(&SLR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    Differential::new(&grammar).unwrap().table("SLR(1)", slr1_table).run().unwrap_or_else(|divergence| panic!("{}", divergence));
+
+    // E : ( E ) | n,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Word(0), Var(0), Word(1)])
+                                                  .add_production([Word(2)])
+                                       .build().unwrap();
+    let lr0_table = NaiveLR1Table::build(&LR0A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    Differential::new(&grammar).unwrap().table("LR(0)", lr0_table).run().unwrap_or_else(|divergence| panic!("{}", divergence));
+
+    // S : L = R | R,
+    // L : * R | id,
+    // R : L,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(1), Word(0), Var(2)])
+                                                  .add_production([Var(2)])
+                                       .new_rule().add_production([Word(1), Var(2)])
+                                                  .add_production([Word(2)])
+                                       .new_rule().add_production([Var(1)])
+                                       .build().unwrap();
+    assert!(Parser::new(grammar.clone(), AutomatonKind::SLR1).is_err());
+    assert!(Parser::new(grammar, AutomatonKind::LALR1).is_ok());
+}
+
+#[test]
+fn parse_trace() {
+    // S : S P | P,
+    // P : ( S ) | ( ),
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Word(0), Var(0), Word(1)])
+                                                  .add_production([Word(0), Word(1)])
+                                       .build().unwrap();
+    let parser = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+
+    let input = vec![0, 1].into_iter().map(Ok::<_,()>);
+    let mut parse = Parse::new(&parser, 0, input, |a: &usize| *a).traced();
+    assert_eq!(parse.by_ref().count(), 4);
+    let trace = parse.trace().unwrap();
+    assert_eq!(trace.steps()[2], TraceStep { stack: vec![0, 1, 4], lookahead: None, action: Action::Reduce(3), goto: Some(3) });
+    assert_eq!(trace.to_string(), "\
+step | stack | lookahead | action
+0    | 0     | 0         | shift 1
+1    | 0 1   | 1         | shift 4
+2    | 0 1 4 | $         | reduce 3, goto 3
+3    | 0 3   | $         | reduce 1, goto 2
+4    | 0 2   | $         | accept
+");
+
+    // The error is recorded once, however often it is reported.
+    let input = vec![1, 1].into_iter().map(Ok::<_,()>);
+    let mut parse = Parse::new(&parser, 0, input, |a: &usize| *a).traced();
+    assert!(parse.next().unwrap().is_err());
+    assert!(parse.next().unwrap().is_err());
+    let names = |token: Option<usize>| ["(", ")"].get(token.unwrap_or(2)).unwrap_or(&"$").to_string();
+    assert_eq!(parse.trace().unwrap().render(names), "\
+step | stack | lookahead | action
+0    | 0     | )         | error
+");
+
+    assert!(Parse::new(&parser, 0, once(Ok::<_,()>(0)), |a: &usize| *a).trace().is_none());
+}
+
+#[test]
+fn spans() {
+    // S : S P | P,
+    // P : ( S ) | ( ),
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Word(0), Var(0), Word(1)])
+                                                  .add_production([Word(0), Word(1)])
+                                       .build().unwrap();
+    let parser = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    let lex = |source: &'static str| source.char_indices()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, c)| Ok::<_, ()>((usize::from(c == ')'), Span::new(i, i + 1))));
+
+    // Each node spans its children.
+    let mut stack = Vec::new();
+    for event in Parse::new(&parser, 0, lex("( ( ) )  ( )"), |word: &(usize, Span)| word.0).spanned() {
+        match event.unwrap() {
+            (Event::Shift(word), span) => {
+                assert_eq!(span, word.1);
+                stack.push(span);
+            },
+            (Event::Reduce { child_count, .. }, span) => {
+                let children = stack.split_off(stack.len() - child_count);
+                assert_eq!(span, Span::new(children[0].start, children[child_count - 1].end));
+                stack.push(span);
+            },
+        }
+    }
+    assert_eq!(stack, vec![Span::new(0, 12)]);
+
+    // Errors point at the offending token, or past the last one at EOF.
+    let error = Parse::new(&parser, 0, lex("( ) )"), |word: &(usize, Span)| word.0).spanned()
+        .find_map(Result::err).unwrap();
+    assert!(matches!(error.error, ParseError::InvalidAction { step: 2, word: Some(1), .. }));
+    assert_eq!(error.span, Span::new(4, 5));
+    let error = Parse::new(&parser, 0, lex("( (  )"), |word: &(usize, Span)| word.0).spanned()
+        .find_map(Result::err).unwrap();
+    assert!(matches!(error.error, ParseError::InvalidAction { step: 3, word: None, .. }));
+    assert_eq!(error.span, Span::empty(6));
+
+    // Empty reductions are empty spans after the preceding token.
+    let nullable = GrammarBuilder::new().new_rule().add_production([Word(0), Var(1), Word(1)])
+                                        .new_rule().add_production([])
+                                        .build().unwrap();
+    let parser = NaiveLR1Table::build(&LALR1A::new(&nullable), |conflict: Conflict| { Err(conflict) }).unwrap();
+    let spans: Vec<Span> = Parse::new(&parser, 0, lex("(  )"), |word: &(usize, Span)| word.0).spanned()
+        .map(|event| event.unwrap().1)
+        .collect();
+    assert_eq!(spans, vec![Span::new(0, 1), Span::empty(1), Span::new(3, 4), Span::new(0, 4)]);
+}
+
+#[test]
+fn incremental_reparse() {
+    // S    : S Item | ε,
+    // Item : ( S ) | x | Opt y,
+    // Opt  : z | ε,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Var(1)])
+                                                  .add_production([])
+                                       .new_rule().add_production([Word(0), Var(0), Word(1)])
+                                                  .add_production([Word(2)])
+                                                  .add_production([Var(2), Word(3)])
+                                       .new_rule().add_production([Word(4)])
+                                                  .add_production([])
+                                       .build().unwrap();
+    let parser = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    let parse = |input: &[usize]| SyntaxTree::parse(&parser, 0, input.to_vec(), |a: &usize| *a);
+
+    // Edits of sampled sentences, valid or not, give the same result as
+    // parsing from scratch.
+    let mut sampler = Sampler::new(&grammar, 7).max_len(24);
+    let sentences: Vec<Vec<usize>> = (0..40).filter_map(|_| sampler.sample(0)).map(|d| d.sentence().to_vec()).collect();
+    let mut rng = XorShift::new(7);
+    for (old, new) in sentences.iter().zip(sentences.iter().rev()) {
+        let tree = parse(old).unwrap();
+        for _ in 0..10 {
+            let start = rng.below(old.len() + 1);
+            let end = start + rng.below(old.len() - start + 1);
+            let from = rng.below(new.len() + 1);
+            let words = new[from..from + rng.below(new.len() - from + 1).min(3)].to_vec();
+
+            let edited: Vec<usize> = old[..start].iter().chain(&words).chain(&old[end..]).copied().collect();
+            let expected = parse(&edited);
+            let actual = tree.reparse(&parser, start..end, words, |a: &usize| *a);
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => {
+                    assert_eq!(actual, expected);
+                    assert_eq!(actual.root().words().into_iter().copied().collect::<Vec<_>>(), edited);
+                },
+                (Err(expected), Err(actual)) => assert_eq!(format!("{:?}", actual), format!("{:?}", expected)),
+                (expected, actual) => panic!("expected {:?}, got {:?}", expected, actual),
+            }
+        }
+    }
+
+    // Replacing a word of the last item of a long list rebuilds that item
+    // and the top of the list only.
+    fn nodes(node: &Arc<SyntaxNode<usize>>, out: &mut Vec<*const SyntaxNode<usize>>) {
+        out.push(Arc::as_ptr(node));
+        for child in node.children() {
+            nodes(child, out);
+        }
+    }
+    let input: Vec<usize> = (0..100).flat_map(|_| vec![0, 2, 4, 3, 1]).collect();
+    let tree = parse(&input).unwrap();
+    let edited = tree.reparse(&parser, 496..497, vec![3], |a: &usize| *a).unwrap();
+    let (mut old_nodes, mut new_nodes) = (Vec::new(), Vec::new());
+    nodes(tree.root(), &mut old_nodes);
+    nodes(edited.root(), &mut new_nodes);
+    let created = new_nodes.iter().filter(|node| !old_nodes.contains(node)).count();
+    assert!(created < 10, "{} of {} nodes created", created, new_nodes.len());
+
+    // Edits outside the input are reported instead of panicking.
+    let error = tree.reparse(&parser, 499..501, vec![], |a: &usize| *a).err().unwrap();
+    assert!(matches!(error, ParseError::InvalidEdit { len: 500, .. }));
+    assert_eq!(error.to_string(), "edit 499..501 is out of bounds for 500 words");
+    let (start, end) = (3, 2);
+    let reversed = tree.reparse(&parser, start..end, vec![], |a: &usize| *a);
+    assert!(matches!(reversed, Err(ParseError::InvalidEdit { len: 500, .. })));
+}
+
+#[test]
+fn completion() {
+    // E : E + T | T,
+    // T : num | ( E ),
+    let grammar = GrammarBuilder::new().new_rule().add_production([Var(0), Word(0), Var(1)])
+                                                  .add_production([Var(1)])
+                                       .new_rule().add_production([Word(1)])
+                                                  .add_production([Word(2), Var(0), Word(3)])
+                                       .build().unwrap();
+    let automaton = LALR1A::new(&grammar);
+    let parser = NaiveLR1Table::build(&automaton, |conflict: Conflict| { Err(conflict) }).unwrap();
+    let complete = |prefix: &[usize]| Completion::new(&parser, 0, prefix.iter().copied().map(Ok::<_, ()>), |a: &usize| *a);
+
+    assert_eq!(complete(&[]).unwrap().words(&parser, &grammar), vec![Some(1), Some(2)]);
+    assert_eq!(complete(&[2, 1]).unwrap().words(&parser, &grammar), vec![Some(0), Some(3)]);
+    assert_eq!(complete(&[2, 1, 0]).unwrap().in_progress(&automaton), vec![0, 1]);
+    assert!(matches!(complete(&[1, 1]), Err(ParseError::InvalidAction { step: 1, word: Some(1), .. })));
+
+    // The continuations of every prefix, compared with those of the
+    // sentences it starts. Any prefix of 4 words can be completed within 9.
+    let sentences: Vec<Vec<usize>> = generate::sentences(&grammar, 0, 9).collect();
+    let mut prefixes: Vec<&[usize]> = sentences.iter()
+        .flat_map(|sentence| (0..=sentence.len().min(3)).map(move |k| &sentence[..k]))
+        .collect();
+    prefixes.sort();
+    prefixes.dedup();
+    for prefix in prefixes {
+        let mut expected: Vec<Option<usize>> = sentences.iter()
+            .filter(|sentence| sentence.starts_with(prefix))
+            .map(|sentence| sentence.get(prefix.len()).copied())
+            .collect();
+        expected.sort();
+        expected.dedup();
+        assert_eq!(complete(prefix).unwrap().words(&parser, &grammar), expected, "after {:?}", prefix);
+    }
+
+    let parser = Parser::new(grammar, AutomatonKind::LALR1).unwrap();
+    assert_eq!(parser.complete(0, vec![Ok::<_, ()>(1)], |a: &usize| *a).unwrap(), vec![None, Some(0)]);
+}
+
+#[test]
+fn error_messages() {
+    use std::error::Error;
+    use crate::grammar::{Describe, Names};
+
+    // Stmt : if c Stmt | if c Stmt else Stmt | other,
+    let grammar = GrammarBuilder::new().new_rule().add_production([Word(0), Word(1), Var(0)])
+                                                  .add_production([Word(0), Word(1), Var(0), Word(2), Var(0)])
+                                                  .add_production([Word(3)])
+                                       .build().unwrap();
+    let names = Names::new(vec!["if", "c", "else", "other"], vec!["Stmt"]).with_grammar(&grammar);
+    let error = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap_err();
+    assert_eq!(error.to_string(), format!("state {} has a shift/reduce conflict on `t2`: shift to state {}, or reduce by production 0",
+        error.state, match error.conflict { Conflict::ShiftReduce { next_state, .. } => next_state, _ => unreachable!() }));
+    assert!(error.named(&names).to_string().ends_with("on `else`: shift to state 6, or reduce by `Stmt -> if c Stmt`"));
+
+    // Input errors are chained as the source.
+    #[derive(Debug)]
+    struct LexError;
+    impl std::fmt::Display for LexError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "invalid character")
+        }
+    }
+    impl Error for LexError {}
+
+    let parser = NaiveLR1Table::build(&LALR1A::new(&grammar), |conflict: Conflict| match conflict {
+        Conflict::ShiftReduce { next_state, .. } => Ok(Action::Shift(next_state)),
+        conflict => Err(conflict),
+    }).unwrap();
+    let parse = |input: Vec<Result<usize, LexError>>| -> Result<(), Box<dyn Error>> {
+        for event in Parse::new(&parser, 0, input.into_iter(), |a: &usize| *a) {
+            event?;
+        }
+        Ok(())
+    };
+    assert!(parse(vec![Ok(3)]).is_ok());
+    let error = parse(vec![Ok(0), Err(LexError)]).unwrap_err();
+    assert_eq!(error.to_string(), "failed to read the input");
+    assert_eq!(error.source().unwrap().to_string(), "invalid character");
+    let error = parse(vec![Ok(0), Ok(0)]).unwrap_err();
+    assert_eq!(error.to_string(), "unexpected `t0` at position 1, in state 1");
+    assert!(error.source().is_none());
+
+    let error = Parse::new(&parser, 0, vec![0, 1].into_iter().map(Ok::<_, ()>), |a: &usize| *a).find_map(Result::err).unwrap();
+    assert_eq!(error.named(&names).to_string(), "unexpected end of input at position 2, in state 4");
+}
+
+#[test]
+fn unknown_words() {
+    use crate::grammar::{Alphabet, Describe, Names, TerminalKind};
+
+    // Expr : num | ( Expr ), with the reserved keyword `let` and whitespace
+    let mut alphabet = Alphabet::new();
+    for name in ["num", "(", ")", "let"] {
+        alphabet.add(name);
+    }
+    let space = alphabet.add_kind("space", TerminalKind::Skip);
+    let grammar = GrammarBuilder::new().alphabet(alphabet)
+                                       .new_rule().add_production([Word(0)])
+                                                  .add_production([Word(1), Var(0), Word(2)])
+                                       .build().unwrap();
+    assert_eq!(grammar.word_count(), 5);
+    assert_eq!(grammar.hygiene().unused_terminals(), [3]);
+
+    let table = NaiveLR1Table::build(&LR1A::new(&grammar), |conflict: Conflict| { Err(conflict) }).unwrap();
+    assert_eq!(table.word_count(), 5);
+    let first_error = |input: Vec<usize>| Parse::new(&table, 0, input.into_iter().map(Ok::<_, ()>), |a: &usize| *a)
+        .find_map(Result::err);
+    let input: Vec<usize> = vec![1, 0, space, 2].into_iter().filter(|&a| a != space).collect();
+    assert!(first_error(input).is_none());
+
+    // A reserved word is rejected by the table, anything else is unknown.
+    assert!(matches!(first_error(vec![1, 3]), Some(ParseError::InvalidAction { step: 1, word: Some(3), .. })));
+    let error = first_error(vec![1, 0, 7]).unwrap();
+    assert!(matches!(error, ParseError::UnknownWord { step: 2, word: 7 }));
+    assert_eq!(error.to_string(), "unknown word 7 at position 2, which is not a terminal of the grammar");
+    let names = Names::default().with_grammar(&grammar);
+    assert_eq!(first_error(vec![3]).unwrap().named(&names).to_string(), "unexpected `let` at position 0, in state 0");
+
+    assert!(matches!(Completion::new(&table, 0, vec![Ok::<_, ()>(1), Ok(9)], |a: &usize| *a),
+        Err(ParseError::UnknownWord { step: 1, word: 9 })));
+    assert!(matches!(SyntaxTree::parse(&table, 0, vec![1, 5, 2], |a: &usize| *a),
+        Err(ParseError::UnknownWord { step: 1, word: 5 })));
+
+    let ll1_table = LL1Table::build(&grammar, Err).unwrap();
+    let error = LL1Parse::new(&ll1_table, 0, vec![1, 8].into_iter().map(Ok::<_, ()>), |a: &usize| *a)
+        .find_map(Result::err);
+    assert!(matches!(error, Some(ParseError::UnknownWord { step: 1, word: 8 })));
+
+    // The largest id does not overflow the bounds check.
+    let max = usize::MAX;
+    assert!(matches!(first_error(vec![1, max]), Some(ParseError::UnknownWord { step: 1, word: usize::MAX })));
+    assert!(matches!(Completion::new(&table, 0, vec![Ok::<_, ()>(max)], |a: &usize| *a),
+        Err(ParseError::UnknownWord { step: 0, word: usize::MAX })));
+    assert!(matches!(SyntaxTree::parse(&table, 0, vec![max], |a: &usize| *a),
+        Err(ParseError::UnknownWord { step: 0, word: usize::MAX })));
+    let error = LL1Parse::new(&ll1_table, 0, vec![max].into_iter().map(Ok::<_, ()>), |a: &usize| *a)
+        .find_map(Result::err);
+    assert!(matches!(error, Some(ParseError::UnknownWord { step: 0, word: usize::MAX })));
+    assert_eq!(ll1_table.predict(0, Some(max)), None);
+}
+
 // =================
 // === UTILITIES ===
 // =================
@@ -80,4 +781,4 @@ fn all_sentences(termcount: usize, maxlen: usize) -> Vec<Vec<usize>> {
         *ws = ws.iter().flat_map(|w| (0..termcount).map(move |b| { w.iter().copied().chain(once(b)).collect() })).collect();
         Some(res)
     }).flatten().collect()
-}
\ No newline at end of file
+}
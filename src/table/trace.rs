@@ -0,0 +1,81 @@
+use std::fmt;
+use super::Action;
+
+/// One step of a parse, recorded by [`Parse::traced`](super::Parse::traced).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// The stack of states before the step, the current one last.
+    pub stack: Vec<usize>,
+    /// The next word, `None` for EOF.
+    pub lookahead: Option<usize>,
+    /// The action taken.
+    pub action: Action,
+    /// For a reduction, the state reached by the goto, or `None` if the goto
+    /// is invalid.
+    pub goto: Option<usize>,
+}
+
+/// The steps of a parse so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    steps: Vec<TraceStep>,
+}
+
+impl Trace {
+    /// The steps, in order.
+    #[must_use]
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.steps
+    }
+
+    /// Renders the steps as a table with one row per step, naming tokens
+    /// with `token_name`. The `Display` implementation names tokens by their
+    /// index, and EOF `$`.
+    pub fn render<N>(&self, mut token_name: N) -> String
+    where
+        N: FnMut(Option<usize>) -> String,
+    {
+        let mut rows = vec![["step", "stack", "lookahead", "action"].iter().map(ToString::to_string).collect::<Vec<_>>()];
+        for (i, step) in self.steps.iter().enumerate() {
+            let stack: Vec<String> = step.stack.iter().map(ToString::to_string).collect();
+            let action = match step.action {
+                Action::Invalid => "error".to_string(),
+                Action::Accept => "accept".to_string(),
+                Action::Shift(state) => format!("shift {}", state),
+                Action::Reduce(production) => match step.goto {
+                    Some(state) => format!("reduce {}, goto {}", production, state),
+                    None => format!("reduce {}, no goto", production),
+                },
+            };
+            rows.push(vec![i.to_string(), stack.join(" "), token_name(step.lookahead), action]);
+        }
+
+        let widths: Vec<usize> = (0..4)
+            .map(|column| rows.iter().map(|row| row[column].chars().count()).max().unwrap())
+            .collect();
+        let mut table = String::new();
+        for row in rows {
+            let cells: Vec<String> = row.iter().zip(&widths)
+                .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+                .collect();
+            table.push_str(cells.join(" | ").trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    pub(super) fn push(&mut self, stack: &[usize], lookahead: Option<usize>, action: Action, goto: Option<usize>) {
+        // The parse keeps repeating its final action, which is only recorded
+        // once.
+        if let Some(TraceStep { action: Action::Accept, .. }) | Some(TraceStep { action: Action::Invalid, .. }) = self.steps.last() {
+            return;
+        }
+        self.steps.push(TraceStep { stack: stack.to_vec(), lookahead, action, goto });
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(|token| token.map_or_else(|| "$".to_string(), |a| a.to_string())))
+    }
+}
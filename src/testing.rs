@@ -0,0 +1,260 @@
+//! Differential testing of parse tables.
+//!
+//! Parse tables built from different automata for the same grammar must
+//! accept the same sentences and produce the same [`Event`]s, since without
+//! conflicts each describes the unique rightmost derivation. On invalid
+//! input, they must fail after shifting the same number of words, though
+//! not necessarily after the same reductions: LALR(1) tables may reduce a
+//! few more times before noticing the error. [`Differential`]
+//! checks this on generated sentences and near misses, which makes it a cheap
+//! regression test when upgrading the crate or adding a new automaton.
+//!
+//! Requires the `testing` feature.
+
+use std::fmt;
+use crate::automata::{LALR1A, LR1A};
+use crate::grammar::Grammar;
+use crate::grammar::generate::{coverage, near_misses, sentences, Sampler};
+use crate::table::{ConstructionError, Event, NaiveLR1Table, Parse, ParseError};
+
+/// A set of named parse tables for one grammar, to be checked against each
+/// other.
+pub struct Differential<'a> {
+    grammar:    &'a Grammar,
+    tables:     Vec<(String, NaiveLR1Table)>,
+    max_len:    usize,
+    limit:      usize,
+    samples:    usize,
+    sample_len: usize,
+    seed:       u64,
+}
+
+/// How each table handled the first sentence they disagree on.
+#[derive(Debug)]
+pub struct Divergence {
+    entry:    usize,
+    sentence: Vec<usize>,
+    runs:     Vec<(String, TableRun)>,
+}
+
+/// The run of one table on one sentence.
+#[derive(Debug)]
+pub struct TableRun {
+    events: Vec<(Event<usize>, usize)>,
+    error:  Option<ParseError<()>>,
+}
+
+impl<'a> Differential<'a> {
+    /// Checks the LALR(1) and LR(1) tables of `grammar`.
+    ///
+    /// # Errors
+    /// Returns the first conflict encountered while building either table.
+    pub fn new(grammar: &'a Grammar) -> Result<Self, ConstructionError> {
+        let lalr1_table = NaiveLR1Table::build(&LALR1A::new(grammar), Err)?;
+        let lr1_table = NaiveLR1Table::build(&LR1A::new(grammar), Err)?;
+        Ok(Self::empty(grammar).table("LALR(1)", lalr1_table).table("LR(1)", lr1_table))
+    }
+
+    /// Checks no tables yet; add them with [`table`](Self::table).
+    #[must_use]
+    pub fn empty(grammar: &'a Grammar) -> Self {
+        Self {
+            grammar,
+            tables:     Vec::new(),
+            max_len:    4,
+            limit:      1000,
+            samples:    100,
+            sample_len: 16,
+            seed:       0,
+        }
+    }
+
+    /// Adds a table built for the same grammar.
+    #[must_use]
+    pub fn table<S: Into<String>>(mut self, name: S, table: NaiveLR1Table) -> Self {
+        self.tables.push((name.into(), table));
+        self
+    }
+
+    /// Enumerates sentences up to this length, 4 by default.
+    #[must_use]
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Enumerates at most this many sentences per entry point, 1000 by
+    /// default.
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Draws this many random sentences per entry point, 100 by default.
+    #[must_use]
+    pub fn samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Limits the length of random sentences, 16 by default. Every input is
+    /// also checked with each of its near misses, whose number grows with
+    /// the length of the input.
+    #[must_use]
+    pub fn sample_len(mut self, sample_len: usize) -> Self {
+        self.sample_len = sample_len;
+        self
+    }
+
+    /// Seeds the random sentences, 0 by default.
+    #[must_use]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Runs every table on enumerated, covering and random sentences of each
+    /// entry point, and on their near misses. Returns the number of inputs
+    /// checked.
+    ///
+    /// # Errors
+    /// Returns the first input on which the tables disagree.
+    pub fn run(&self) -> Result<usize, Divergence> {
+        let mut count = 0;
+        for entry in 0..self.grammar.entry_points().len() {
+            let mut sampler = Sampler::new(self.grammar, self.seed).max_len(self.sample_len);
+            let valid: Vec<Vec<usize>> = sentences(self.grammar, entry, self.max_len).take(self.limit)
+                .chain(coverage(self.grammar, entry).into_iter().map(|derivation| derivation.sentence().to_vec()))
+                .chain((0..self.samples).filter_map(|_| sampler.sample(entry)).map(|derivation| derivation.sentence().to_vec()))
+                .collect();
+
+            for sentence in &valid {
+                self.check(entry, sentence)?;
+                for miss in near_misses(self.grammar, entry, sentence) {
+                    self.check(entry, &miss)?;
+                    count += 1;
+                }
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Runs every table on `sentence` from entry point `entry`.
+    ///
+    /// # Errors
+    /// Returns the runs of all tables if they disagree.
+    pub fn check(&self, entry: usize, sentence: &[usize]) -> Result<(), Divergence> {
+        let runs: Vec<(String, TableRun)> = self.tables.iter()
+            .map(|(name, table)| (name.clone(), TableRun::new(table, entry, sentence)))
+            .collect();
+
+        let agree = runs.windows(2).all(|pair| {
+            let (left, right) = (&pair[0].1, &pair[1].1);
+            match (left.accepted(), right.accepted()) {
+                (true, true) => left.events.iter().map(|(event, _)| event).eq(right.events.iter().map(|(event, _)| event)),
+                (false, false) => left.shift_count() == right.shift_count(),
+                _ => false,
+            }
+        });
+
+        if agree {
+            Ok(())
+        } else {
+            Err(Divergence { entry, sentence: sentence.to_vec(), runs })
+        }
+    }
+}
+
+impl Divergence {
+    /// The entry point parsed from.
+    #[must_use]
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// The input the tables disagree on.
+    #[must_use]
+    pub fn sentence(&self) -> &[usize] {
+        &self.sentence
+    }
+
+    /// The name and run of each table, in the order they were added.
+    #[must_use]
+    pub fn runs(&self) -> &[(String, TableRun)] {
+        &self.runs
+    }
+
+    /// The index of the first event on which some tables differ, which is
+    /// the length of the shorter run if one is a prefix of the other.
+    #[must_use]
+    pub fn position(&self) -> usize {
+        let first = &self.runs[0].1.events;
+        self.runs.iter()
+            .map(|(_, run)| {
+                first.iter().zip(&run.events)
+                    .position(|((left, _), (right, _))| left != right)
+                    .unwrap_or_else(|| first.len().min(run.events.len()))
+            })
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "tables diverge on input {:?} from entry point {} at event {}", self.sentence, self.entry, self.position())?;
+        for (name, run) in &self.runs {
+            writeln!(f, "{}:", name)?;
+            for (event, state) in &run.events {
+                writeln!(f, "    {:?} -> state {}", event, state)?;
+            }
+            match &run.error {
+                Some(error) => writeln!(f, "    rejected: {:?}", error)?,
+                None => writeln!(f, "    accepted")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TableRun {
+    fn new(table: &NaiveLR1Table, entry: usize, sentence: &[usize]) -> Self {
+        let mut parse = Parse::new(table, entry, sentence.iter().copied().map(Ok::<_, ()>), |a: &usize| *a);
+        let mut events = Vec::new();
+        let mut error = None;
+        while let Some(result) = parse.next() {
+            match result {
+                Ok(event) => events.push((event, *parse.states().last().unwrap())),
+                Err(err) => {
+                    error = Some(err);
+                    break;
+                },
+            }
+        }
+        Self { events, error }
+    }
+
+    /// The events, each with the state the table was in after it.
+    #[must_use]
+    pub fn events(&self) -> &[(Event<usize>, usize)] {
+        &self.events
+    }
+
+    /// The error the input was rejected with, if any.
+    #[must_use]
+    pub fn error(&self) -> Option<&ParseError<()>> {
+        self.error.as_ref()
+    }
+
+    /// Returns `true` if the input was accepted.
+    #[must_use]
+    pub fn accepted(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn shift_count(&self) -> usize {
+        self.events.iter().filter(|(event, _)| matches!(event, Event::Shift(_))).count()
+    }
+}